@@ -0,0 +1,293 @@
+use crate::{
+    bvh::bvh::{split_method::Middle, Bvh},
+    camera::{perspective::Perspective, projection::Camera},
+    render::{render, save_png},
+    shape::{hittable::Shape, sphere::Sphere, triangle_mesh::TriangleMesh},
+    transform::Transform,
+    vec3::Vec3,
+};
+use std::{
+    ops::{Add, Mul, Range},
+    path::PathBuf,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Catmull-Rom spline through the keyframes.
+    Spline,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+}
+
+/// Value that changes over time, given by keyframes sorted by time. Before the
+/// first and after the last keyframe the track holds its end values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    pub keyframes: Vec<Keyframe<T>>,
+    pub interpolation: Interpolation,
+}
+
+impl<T> Track<T>
+where
+    T: Copy + Add<T, Output = T> + Mul<f64, Output = T>,
+{
+    pub fn new(keyframes: Vec<Keyframe<T>>, interpolation: Interpolation) -> Track<T> {
+        assert!(!keyframes.is_empty(), "no keyframes");
+        assert!(
+            keyframes.windows(2).all(|k| k[0].time < k[1].time),
+            "keyframes not sorted by time"
+        );
+        Track {
+            keyframes,
+            interpolation,
+        }
+    }
+
+    pub fn constant(value: T) -> Track<T> {
+        Track::new(vec![Keyframe { time: 0.0, value }], Interpolation::Linear)
+    }
+
+    pub fn evaluate(&self, time: f64) -> T {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return keys[0].value;
+        }
+        if next == keys.len() {
+            return keys[keys.len() - 1].value;
+        }
+
+        let (k1, k2) = (keys[next - 1], keys[next]);
+        let t = (time - k1.time) / (k2.time - k1.time);
+        match self.interpolation {
+            Interpolation::Linear => k1.value * (1.0 - t) + k2.value * t,
+            Interpolation::Spline => {
+                let p0 = keys[next.saturating_sub(2)].value;
+                let p3 = keys[(next + 1).min(keys.len() - 1)].value;
+                let (t2, t3) = (t * t, t * t * t);
+                p0 * (0.5 * (-t + 2.0 * t2 - t3))
+                    + k1.value * (0.5 * (2.0 - 5.0 * t2 + 3.0 * t3))
+                    + k2.value * (0.5 * (t + 4.0 * t2 - 3.0 * t3))
+                    + p3 * (0.5 * (t3 - t2))
+            }
+        }
+    }
+
+    /// Values whose convex hull contains every value the track takes during
+    /// `times`. Spline segments contribute the control points of the equivalent
+    /// cubic Bezier curve.
+    pub fn hull(&self, times: Range<f64>) -> Vec<T> {
+        let keys = &self.keyframes;
+        let mut points = vec![self.evaluate(times.start), self.evaluate(times.end)];
+        for i in 1..keys.len() {
+            let (k1, k2) = (keys[i - 1], keys[i]);
+            if k2.time <= times.start || k1.time >= times.end {
+                continue;
+            }
+            match self.interpolation {
+                Interpolation::Linear => points.extend([k1.value, k2.value]),
+                Interpolation::Spline => {
+                    let p0 = keys[i.saturating_sub(2)].value;
+                    let p3 = keys[(i + 1).min(keys.len() - 1)].value;
+                    points.extend([
+                        k1.value,
+                        k1.value + (k2.value + p0 * -1.0) * (1.0 / 6.0),
+                        k2.value + (p3 + k1.value * -1.0) * (-1.0 / 6.0),
+                        k2.value,
+                    ]);
+                }
+            }
+        }
+        points
+    }
+}
+
+/// Keyframed camera. The focus distance follows the distance between
+/// `look_from` and `look_at`.
+#[derive(Clone, Debug)]
+pub struct CameraAnimation {
+    pub look_from: Track<Vec3>,
+    pub look_at: Track<Vec3>,
+    pub vertical_fov: Track<f64>,
+    pub up: Vec3,
+    pub aperture: f64,
+}
+
+impl CameraAnimation {
+    pub fn camera_at(&self, time: f64, aspect_ratio: f64) -> Camera {
+        let look_from = self.look_from.evaluate(time);
+        let look_at = self.look_at.evaluate(time);
+        Perspective::new(
+            look_from,
+            look_at,
+            self.up,
+            self.vertical_fov.evaluate(time),
+            aspect_ratio,
+            self.aperture,
+            (look_from - look_at).length(),
+        )
+        .into()
+    }
+}
+
+/// Object with an optional keyframed transform applied on top of its geometry.
+#[derive(Clone, Debug)]
+pub struct Animated<T> {
+    pub object: T,
+    pub transform: Option<Track<Transform>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub camera: CameraAnimation,
+    pub spheres: Vec<Animated<Sphere>>,
+    pub meshes: Vec<Animated<TriangleMesh>>,
+    pub frames_per_second: f64,
+}
+
+impl Animation {
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        frame as f64 / self.frames_per_second
+    }
+
+    fn transforms_at(&self, time: f64) -> Vec<Option<Transform>> {
+        let spheres = self.spheres.iter().map(|s| &s.transform);
+        let meshes = self.meshes.iter().map(|m| &m.transform);
+        spheres
+            .chain(meshes)
+            .map(|track| track.as_ref().map(|t| t.evaluate(time)))
+            .collect()
+    }
+
+    pub fn spheres_at(&self, time: f64) -> Vec<Sphere> {
+        self.spheres
+            .iter()
+            .map(|animated| {
+                let mut sphere = animated.object.clone();
+                if let Some(track) = &animated.transform {
+                    let transform = track.evaluate(time);
+                    sphere.center = transform.apply_point(sphere.center);
+                    sphere.radius *= transform.scale;
+                }
+                sphere
+            })
+            .collect()
+    }
+
+    pub fn meshes_at(&self, time: f64) -> Vec<TriangleMesh> {
+        self.meshes
+            .iter()
+            .map(|animated| {
+                let mut mesh = animated.object.clone();
+                if let Some(track) = &animated.transform {
+                    let transform = track.evaluate(time);
+                    for v in mesh.vertices.iter_mut() {
+                        *v = transform.apply_point(*v);
+                    }
+                }
+                mesh
+            })
+            .collect()
+    }
+
+    /// Renders `frames` and saves frame `n` to `output(n)`.
+    ///
+    /// The bvh is only rebuilt for frames where some object transform differs
+    /// from the frame the current bvh was built for, so camera only animation
+    /// builds the bvh once.
+    pub fn render_frames(
+        &self,
+        frames: Range<u32>,
+        resolution: (u32, u32),
+        samples_per_pixel: u32,
+        output: impl Fn(u32) -> PathBuf,
+    ) {
+        let aspect_ratio = resolution.0 as f64 / resolution.1 as f64;
+        let mut frame = frames.start;
+        while frame < frames.end {
+            let time = self.frame_time(frame);
+            let transforms = self.transforms_at(time);
+            let mut end = frame + 1;
+            while end < frames.end && self.transforms_at(self.frame_time(end)) == transforms {
+                end += 1;
+            }
+
+            let meshes = self.meshes_at(time);
+            let mut world: Vec<Shape> = self.spheres_at(time).into_iter().map(Shape::Sph).collect();
+            world.extend(
+                meshes
+                    .iter()
+                    .flat_map(|m| m.triangles().map(Shape::Triangle)),
+            );
+            let bvh = Bvh::build(&mut world, Middle);
+
+            for f in frame..end {
+                let camera = self.camera.camera_at(self.frame_time(f), aspect_ratio);
+                let buffer = render(resolution, samples_per_pixel, &bvh, &camera);
+                save_png(&output(f), resolution, &buffer);
+            }
+            frame = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn keyframes() -> Vec<Keyframe<f64>> {
+        vec![
+            Keyframe {
+                time: 0.0,
+                value: 0.0,
+            },
+            Keyframe {
+                time: 1.0,
+                value: 2.0,
+            },
+            Keyframe {
+                time: 3.0,
+                value: -1.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn linear_track() {
+        let track = Track::new(keyframes(), Interpolation::Linear);
+        assert_approx_eq!(track.evaluate(-1.0), 0.0);
+        assert_approx_eq!(track.evaluate(0.5), 1.0);
+        assert_approx_eq!(track.evaluate(2.0), 0.5);
+        assert_approx_eq!(track.evaluate(4.0), -1.0);
+    }
+
+    #[test]
+    fn spline_track_passes_through_keyframes() {
+        let track = Track::new(keyframes(), Interpolation::Spline);
+        for key in keyframes() {
+            assert_approx_eq!(track.evaluate(key.time), key.value);
+        }
+        let mid = track.evaluate(0.5);
+        assert!(mid > 0.0 && mid < 2.5);
+    }
+
+    #[test]
+    fn hull_bounds_track() {
+        for interpolation in [Interpolation::Linear, Interpolation::Spline] {
+            let track = Track::new(keyframes(), interpolation);
+            let hull = track.hull(0.5..2.5);
+            let min = hull.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = hull.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            for i in 0..=100 {
+                let value = track.evaluate(0.5 + 2.0 * i as f64 / 100.0);
+                assert!(min <= value && value <= max);
+            }
+        }
+    }
+}
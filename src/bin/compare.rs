@@ -0,0 +1,60 @@
+use raytrace_rust::compare::{compare, DEFAULT_PIXELS_PER_DEGREE};
+use raytrace_rust::linear_image::LinearImage;
+use raytrace_rust::render::save_png;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: compare <reference> <test> [--diff <output.png>] [--ppd <pixels per degree>]"
+    );
+    exit(2)
+}
+
+fn main() {
+    let mut paths = vec![];
+    let mut diff: Option<PathBuf> = None;
+    let mut pixels_per_degree = DEFAULT_PIXELS_PER_DEGREE;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--diff" => diff = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--ppd" => {
+                pixels_per_degree = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [reference, test] = &paths[..] else {
+        usage()
+    };
+
+    let reference = LinearImage::load(Path::new(reference)).unwrap();
+    let test = LinearImage::load(Path::new(test)).unwrap();
+    if (reference.width, reference.height) != (test.width, test.height) {
+        eprintln!("image sizes differ");
+        exit(1);
+    }
+
+    let comparison = compare(&reference, &test, pixels_per_degree);
+    let m = comparison.metrics;
+    println!("MSE:    {:.6e}", m.mse);
+    println!("RMSE:   {:.6e}", m.rmse);
+    println!("relMSE: {:.6e}", m.rel_mse);
+    println!("PSNR:   {:.3} dB", m.psnr);
+    println!("SSIM:   {:.6}", m.ssim);
+    println!("FLIP:   {:.6}", m.flip);
+
+    if let Some(path) = diff {
+        save_png(
+            &path,
+            (comparison.width, comparison.height),
+            &comparison.error_image(),
+        );
+    }
+}
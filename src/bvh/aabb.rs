@@ -1,8 +1,4 @@
-use std::{
-    f64::{INFINITY, NEG_INFINITY},
-    mem::swap,
-    ops::Range,
-};
+use std::{mem::swap, ops::Range};
 
 use crate::{ray::Ray, vec3::Vec3};
 
@@ -15,8 +11,8 @@ pub struct AxisAlignedBoundingBox {
 impl AxisAlignedBoundingBox {
     pub fn null_box() -> AxisAlignedBoundingBox {
         AxisAlignedBoundingBox {
-            min: Vec3::new(INFINITY, INFINITY, INFINITY),
-            max: Vec3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
+            min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
         }
     }
 
@@ -93,8 +89,8 @@ impl AxisAlignedBoundingBox {
     where
         T: IntoIterator<Item = AxisAlignedBoundingBox>,
     {
-        let mut min = [INFINITY; 3];
-        let mut max = [NEG_INFINITY; 3];
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
 
         for bbox in bounding_boxes {
             for a in 0..3 {
@@ -121,55 +117,66 @@ impl AxisAlignedBoundingBox {
 #[test]
 fn aabb_hit() {
     let aabb1 = AxisAlignedBoundingBox {
-        min: Vec3::new(INFINITY, INFINITY, INFINITY),
-        max: Vec3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
+        min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
     };
     let aabb2 = AxisAlignedBoundingBox {
-        min: Vec3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
-        max: Vec3::new(INFINITY, INFINITY, INFINITY),
+        min: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        max: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
     };
     let dir = Vec3::new(1.0, 1.0, 1.0);
     let origin = Vec3::origin();
 
-    let hit1 = aabb1.hit(&Ray::new(origin, dir), NEG_INFINITY..INFINITY);
-    let hit2 = aabb2.hit(&Ray::new(origin, dir), 0.0..1.0);
+    let hit1 = aabb1.hit(
+        &Ray::new(origin, dir, 0.0),
+        f64::NEG_INFINITY..f64::INFINITY,
+    );
+    let hit2 = aabb2.hit(&Ray::new(origin, dir, 0.0), 0.0..1.0);
     println!("{}, {}", hit1, hit2);
-    assert!(hit1 == false);
-    assert!(hit2 == true);
+    assert!(!hit1);
+    assert!(hit2);
 }
 
 #[test]
 fn aabb_hit_infinite() {
     let aabb = AxisAlignedBoundingBox {
-        min: Vec3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
-        max: Vec3::new(INFINITY, INFINITY, INFINITY),
+        min: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        max: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
     };
 
     let origin = Vec3::origin();
     let direction = Vec3::new(1.0, 1.0, 1.0);
-    let mut ray = Ray { origin, direction };
+    let mut ray = Ray {
+        origin,
+        direction,
+        time: 0.0,
+    };
     let mut hit = aabb.hit(&ray, 0.0..1.0);
-    assert!(hit == true);
+    assert!(hit);
     ray.origin = Vec3::new(1.0, 0.0, 0.0);
     ray.direction = Vec3::new(-1.0, 0.0, 0.0);
     hit = aabb.hit(&ray, 0.0..1.0);
-    assert!(hit == true);
+    assert!(hit);
 }
 
 #[test]
 fn aabb_hit_neg_infinite() {
     let aabb = AxisAlignedBoundingBox {
-        min: Vec3::new(INFINITY, INFINITY, INFINITY),
-        max: Vec3::new(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY),
+        min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
     };
 
     let origin = Vec3::origin();
     let direction = Vec3::new(1.0, 1.0, 1.0);
-    let mut ray = Ray { origin, direction };
+    let mut ray = Ray {
+        origin,
+        direction,
+        time: 0.0,
+    };
     let mut hit = aabb.hit(&ray, 0.0..1.0);
-    assert!(hit == false);
+    assert!(!hit);
     ray.origin = Vec3::new(1.0, 0.0, 0.0);
     ray.direction = Vec3::new(-1.0, 0.0, 0.0);
     hit = aabb.hit(&ray, 0.0..1.0);
-    assert!(hit == false);
+    assert!(!hit);
 }
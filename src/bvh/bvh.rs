@@ -190,7 +190,7 @@ fn select_axis(primitives: &[Shape]) -> (usize, AxisAlignedBoundingBox) {
 
 impl<'a, 'b, 'c> Bvh<'a> {
     pub fn build<T: Split + Copy>(primitives: &'a mut [Shape<'b, 'c>], method: T) -> Bvh<'a> {
-        assert!(primitives.len() > 0, "no primitives");
+        assert!(!primitives.is_empty(), "no primitives");
 
         let bounds = primitives.bounding_box();
 
@@ -202,18 +202,16 @@ impl<'a, 'b, 'c> Bvh<'a> {
                     right: Box::new(Bvh::build(right, method)),
                 },
             },
-            (left, None) => {
-                return Bvh {
-                    bounds,
-                    contents: BvhContents::Leaf(left),
-                }
-            }
+            (left, None) => Bvh {
+                bounds,
+                contents: BvhContents::Leaf(left),
+            },
         }
     }
 }
 
 impl<'a> Hittable for Bvh<'a> {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         if !self.bounds.hit(ray, t_range.clone()) {
             return None;
         }
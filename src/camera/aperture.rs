@@ -0,0 +1,169 @@
+use crate::{linear_image::LinearImage, random, utility::luminance, vec3::Vec3};
+use image::ImageResult;
+use std::{f64::consts::TAU, io, path::Path, sync::Arc};
+
+/// Shape of the lens opening, which is the shape defocused highlights take.
+#[derive(Clone, Debug, Default)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    /// Regular polygon, created by [`Aperture::polygon`].
+    Polygon(Polygon),
+    Mask(Arc<ApertureMask>),
+}
+
+/// Regular polygon formed by `blades` straight blades, rotated counterclockwise
+/// by `rotation` radians.
+#[derive(Clone, Copy, Debug)]
+pub struct Polygon {
+    blades: u32,
+    rotation: f64,
+}
+
+impl Aperture {
+    /// Polygon formed by `blades` straight blades, rotated counterclockwise by
+    /// `rotation` degrees. Fails with fewer than 3 blades.
+    pub fn polygon(blades: u32, rotation: f64) -> io::Result<Aperture> {
+        match blades >= 3 {
+            true => Ok(Aperture::Polygon(Polygon {
+                blades,
+                rotation: rotation.to_radians(),
+            })),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a polygonal aperture needs at least 3 blades",
+            )),
+        }
+    }
+
+    /// Uniformly distributed point on the aperture, which fits in the unit disc
+    /// for circles and polygons and in the square around it for masks.
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Circle => random_vector_in_unit_disc(),
+            Aperture::Polygon(polygon) => {
+                random_vector_in_polygon(polygon.blades, polygon.rotation)
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+/// Aperture given by the transmission of a grayscale image covering the square
+/// from `(-1, -1)` to `(1, 1)`, with its first row at the top.
+#[derive(Debug)]
+pub struct ApertureMask {
+    pub width: u32,
+    pub height: u32,
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// Fails if `transmission` does not hold `width * height` values or lets
+    /// no light through.
+    pub fn new(width: u32, height: u32, transmission: &[f64]) -> io::Result<ApertureMask> {
+        if transmission.len() != width as usize * height as usize {
+            return Err(invalid("aperture mask size does not match its resolution"));
+        }
+        let total: f64 = transmission.iter().map(|t| t.max(0.0)).sum();
+        if total <= 0.0 {
+            return Err(invalid("aperture mask is fully opaque"));
+        }
+        let cdf = transmission
+            .iter()
+            .scan(0.0, |acc, t| {
+                *acc += t.max(0.0) / total;
+                Some(*acc)
+            })
+            .collect();
+        Ok(ApertureMask { width, height, cdf })
+    }
+
+    pub fn load(path: &Path) -> ImageResult<ApertureMask> {
+        let img = LinearImage::load_data(path)?;
+        let transmission: Vec<f64> = img.pixels.iter().map(|p| luminance(*p)).collect();
+        Ok(ApertureMask::new(img.width, img.height, &transmission)?)
+    }
+
+    /// Point distributed in proportion to the transmission of the mask.
+    fn sample(&self) -> Vec3 {
+        let pick = random::uniform();
+        let index = self
+            .cdf
+            .partition_point(|c| *c <= pick)
+            .min(self.cdf.len() - 1);
+        let x = (index as u32 % self.width) as f64 + random::uniform();
+        let y = (index as u32 / self.width) as f64 + random::uniform();
+        Vec3::new(
+            2.0 * x / self.width as f64 - 1.0,
+            1.0 - 2.0 * y / self.height as f64,
+            0.0,
+        )
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn random_vector_in_unit_disc() -> Vec3 {
+    let r = random::uniform().sqrt();
+    let theta = random::uniform_range(0.0..TAU);
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+/// Point in the regular polygon inscribed in the unit circle, found by picking
+/// one of the equally large triangles between the center and an edge.
+fn random_vector_in_polygon(blades: u32, rotation: f64) -> Vec3 {
+    let edge = ((random::uniform() * blades as f64) as u32).min(blades - 1);
+    let angle = |i: u32| rotation + TAU * i as f64 / blades as f64;
+    let a = Vec3::new(angle(edge).cos(), angle(edge).sin(), 0.0);
+    let b = Vec3::new(angle(edge + 1).cos(), angle(edge + 1).sin(), 0.0);
+
+    let (mut s, mut t) = (random::uniform(), random::uniform());
+    if s + t > 1.0 {
+        (s, t) = (1.0 - s, 1.0 - t);
+    }
+    s * a + t * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn polygon_samples_stay_inside() {
+        random::seed(5);
+        let blades = 6;
+        let rotation = 15.0f64.to_radians();
+        let apothem = (PI / blades as f64).cos();
+        for _ in 0..1000 {
+            let p = random_vector_in_polygon(blades, rotation);
+            for i in 0..blades {
+                let normal_angle = rotation + (i as f64 + 0.5) * TAU / blades as f64;
+                let normal = Vec3::new(normal_angle.cos(), normal_angle.sin(), 0.0);
+                assert!(p.dot(normal) <= apothem + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_samples_follow_transmission() {
+        random::seed(5);
+        // Only the upper right quadrant lets light through.
+        let mask = ApertureMask::new(2, 2, &[0.0, 1.0, 0.0, 0.0]).unwrap();
+        for _ in 0..1000 {
+            let p = mask.sample();
+            assert!((0.0..=1.0).contains(&p.x) && (0.0..=1.0).contains(&p.y));
+        }
+    }
+
+    #[test]
+    fn invalid_apertures_are_rejected() {
+        assert!(Aperture::polygon(2, 0.0).is_err());
+        assert!(Aperture::polygon(3, 0.0).is_ok());
+        assert!(ApertureMask::new(2, 2, &[0.0; 4]).is_err());
+        assert!(ApertureMask::new(2, 2, &[1.0; 3]).is_err());
+    }
+}
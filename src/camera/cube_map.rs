@@ -0,0 +1,148 @@
+use super::projection::{Frame, Projection, Shutter};
+use crate::{ray::Ray, vec3::Vec3};
+
+/// Face of a cube map, named by its direction relative to the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeFace {
+    Front,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// Faces in the 3 by 2 layout of the image, top row first.
+    pub const LAYOUT: [[CubeFace; 3]; 2] = [
+        [CubeFace::Left, CubeFace::Front, CubeFace::Right],
+        [CubeFace::Down, CubeFace::Back, CubeFace::Up],
+    ];
+
+    /// Forward and up direction of the face in the camera frame. The up
+    /// direction of the up face points away from the front face, and that of
+    /// the down face points towards it.
+    fn axes(self) -> (Vec3, Vec3) {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+        match self {
+            CubeFace::Front => (-z, y),
+            CubeFace::Back => (z, y),
+            CubeFace::Left => (-x, y),
+            CubeFace::Right => (x, y),
+            CubeFace::Up => (y, z),
+            CubeFace::Down => (-y, -z),
+        }
+    }
+
+    /// Direction through the point `(a, b)` of the face, both in `[-1, 1]`.
+    pub fn direction(self, a: f64, b: f64) -> Vec3 {
+        let (forward, up) = self.axes();
+        let right = Vec3::cross(forward, up);
+        forward + a * right + b * up
+    }
+}
+
+/// Six 90 degree views around the camera arranged as in
+/// [`CubeFace::LAYOUT`]. The image should be 3 by 2 faces of equal size.
+#[derive(Clone, Debug)]
+pub struct CubeMap {
+    pub frame: Frame,
+    pub shutter: Shutter,
+}
+
+impl CubeMap {
+    pub fn new(look_from: Vec3, look_at: Vec3, up: Vec3) -> CubeMap {
+        CubeMap {
+            frame: Frame::new(look_from, look_at, up),
+            shutter: Shutter::default(),
+        }
+    }
+}
+
+impl Projection for CubeMap {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        let column = ((s * 3.0) as usize).min(2);
+        let row = ((2.0 - t * 2.0) as usize).min(1);
+        let a = 2.0 * (s * 3.0 - column as f64) - 1.0;
+        let b = 2.0 * (t * 2.0 - (1 - row) as f64) - 1.0;
+        let direction = CubeFace::LAYOUT[row][column].direction(a, b);
+        Some(self.frame.ray(direction, self.shutter.sample_time()))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        let local = self.frame.to_local(point - self.frame.origin);
+        // The face looking most directly at the point sees it.
+        let depth =
+            |(row, column): (usize, usize)| local.dot(CubeFace::LAYOUT[row][column].axes().0);
+        let (row, column) = (0..6)
+            .map(|i| (i / 3, i % 3))
+            .max_by(|a, b| depth(*a).total_cmp(&depth(*b)))?;
+        let (forward, up) = CubeFace::LAYOUT[row][column].axes();
+        let depth = local.dot(forward);
+        if depth <= 0.0 {
+            return None;
+        }
+        let a = local.dot(Vec3::cross(forward, up)) / depth;
+        let b = local.dot(up) / depth;
+        Some((
+            (column as f64 + (a + 1.0) / 2.0) / 3.0,
+            ((1 - row) as f64 + (b + 1.0) / 2.0) / 2.0,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn project_inverts_get_ray() {
+        let camera = CubeMap::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        for st in [
+            (0.1, 0.9),
+            (0.5, 0.6),
+            (0.8, 0.7),
+            (0.2, 0.3),
+            (0.45, 0.1),
+            (0.9, 0.4),
+        ] {
+            let ray = camera.get_ray(st).unwrap();
+            let (s, t) = camera.project(ray.origin + 5.0 * ray.direction).unwrap();
+            assert_approx_eq!(s, st.0);
+            assert_approx_eq!(t, st.1);
+        }
+    }
+
+    #[test]
+    fn face_centers_look_along_axes() {
+        let camera = CubeMap::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let direction = |st| camera.get_ray(st).unwrap().direction;
+        let cases = [
+            ((0.5 / 3.0, 0.75), Vec3::new(-1.0, 0.0, 0.0)),
+            ((1.5 / 3.0, 0.75), Vec3::new(0.0, 0.0, -1.0)),
+            ((2.5 / 3.0, 0.75), Vec3::new(1.0, 0.0, 0.0)),
+            ((0.5 / 3.0, 0.25), Vec3::new(0.0, -1.0, 0.0)),
+            ((1.5 / 3.0, 0.25), Vec3::new(0.0, 0.0, 1.0)),
+            ((2.5 / 3.0, 0.25), Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        for (st, expected) in cases {
+            assert_approx_eq!((direction(st) - expected).length(), 0.0);
+        }
+
+        // The top edge of the front face meets the edge of the up face.
+        let top = direction((1.5 / 3.0, 1.0));
+        assert_approx_eq!((top - Vec3::new(0.0, 1.0, -1.0).unitize()).length(), 0.0);
+    }
+}
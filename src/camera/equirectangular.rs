@@ -0,0 +1,78 @@
+use super::projection::{Frame, Projection, Shutter};
+use crate::{ray::Ray, vec3::Vec3};
+use std::f64::consts::{PI, TAU};
+
+/// Latitude-longitude panorama covering every direction around the camera.
+/// Longitude grows left to right with the view direction in the middle of the
+/// image, latitude grows bottom to top. The image should be twice as wide as
+/// it is high.
+#[derive(Clone, Debug)]
+pub struct Equirectangular {
+    pub frame: Frame,
+    pub shutter: Shutter,
+}
+
+impl Equirectangular {
+    pub fn new(look_from: Vec3, look_at: Vec3, up: Vec3) -> Equirectangular {
+        Equirectangular {
+            frame: Frame::new(look_from, look_at, up),
+            shutter: Shutter::default(),
+        }
+    }
+}
+
+impl Projection for Equirectangular {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        let longitude = (s - 0.5) * TAU;
+        let latitude = (t - 0.5) * PI;
+        let direction = Vec3::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+        Some(self.frame.ray(direction, self.shutter.sample_time()))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        let offset = point - self.frame.origin;
+        // A point at the camera has no direction.
+        if offset.length_sq() == 0.0 {
+            return None;
+        }
+        let local = self.frame.to_local(offset.unitize());
+        let longitude = local.x.atan2(-local.z);
+        let latitude = local.y.clamp(-1.0, 1.0).asin();
+        Some((longitude / TAU + 0.5, latitude / PI + 0.5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn maps_image_to_sphere() {
+        let camera = Equirectangular::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let direction = |st| camera.get_ray(st).unwrap().direction;
+        let cases = [
+            ((0.5, 0.5), Vec3::new(0.0, 0.0, -1.0)),
+            ((0.75, 0.5), Vec3::new(1.0, 0.0, 0.0)),
+            ((0.25, 0.5), Vec3::new(-1.0, 0.0, 0.0)),
+            ((0.0, 0.5), Vec3::new(0.0, 0.0, 1.0)),
+            ((0.3, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+        ];
+        for (st, expected) in cases {
+            assert_approx_eq!((direction(st) - expected).length(), 0.0);
+        }
+        let (s, t) = camera.project(Vec3::new(1.0, 1.0, 0.0)).unwrap();
+        assert_approx_eq!(s, 0.75);
+        assert_approx_eq!(t, 0.75);
+        assert_eq!(camera.project(Vec3::origin()), None);
+    }
+}
@@ -0,0 +1,138 @@
+use super::projection::{Camera, Projection};
+use crate::{ray::Ray, render::Film, utility::luminance, vec3::Vec3};
+
+/// Exposure settings of a physical camera. Scene radiance is taken to be in
+/// cd/m², so that typical settings give a well exposed image of scenes lit as
+/// brightly as the real world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exposure {
+    pub f_number: f64,
+    /// Time the shutter is open in seconds.
+    pub shutter_time: f64,
+    pub iso: f64,
+}
+
+impl Exposure {
+    /// Exposure value at ISO 100.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso).log2()
+    }
+
+    /// Factor scaling radiance to pixel values, following the saturation based
+    /// sensitivity of ISO 12232 with a lens attenuation of 0.65, so that the
+    /// brightest unclipped luminance maps to 1.
+    pub fn scale(&self) -> f64 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
+
+/// Camera scaling the radiance along its rays by the exposure of physical
+/// settings, made with [`Camera::with_exposure`].
+#[derive(Clone, Debug)]
+pub struct Exposed {
+    pub camera: Box<Camera>,
+    pub exposure: Exposure,
+}
+
+impl Projection for Exposed {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        self.camera.get_ray(st)
+    }
+
+    fn chief_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        self.camera.chief_ray(st)
+    }
+
+    fn get_weighted_ray(&self, st: (f64, f64)) -> Option<(Ray, f64)> {
+        let scale = self.exposure.scale();
+        self.camera
+            .get_weighted_ray(st)
+            .map(|(ray, weight)| (ray, weight * scale))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        self.camera.project(point)
+    }
+}
+
+/// Middle gray the log-average luminance is mapped to by [`auto_exposure`].
+pub const KEY_VALUE: f64 = 0.18;
+
+/// Exposure scale mapping the log-average luminance of `film` to middle gray,
+/// as a camera's automatic exposure would.
+pub fn auto_exposure(film: &Film) -> f64 {
+    let delta = 1e-4;
+    let (sum, count) = (0..film.pixels.len())
+        .filter(|i| film.samples[*i] > 0)
+        .map(|i| (delta + luminance(film.mean(i))).ln())
+        .fold((0.0, 0), |(sum, count), l| (sum + l, count + 1));
+    match count {
+        0 => 1.0,
+        _ => KEY_VALUE / (sum / count as f64).exp(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::equirectangular::Equirectangular;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn stops_double_exposure() {
+        let sunny_16 = Exposure {
+            f_number: 16.0,
+            shutter_time: 1.0 / 100.0,
+            iso: 100.0,
+        };
+        assert_approx_eq!(sunny_16.ev100(), 25600.0f64.log2());
+
+        let base = sunny_16.scale();
+        let longer = Exposure {
+            shutter_time: 2.0 / 100.0,
+            ..sunny_16
+        };
+        let wider = Exposure {
+            f_number: 16.0 / 2.0f64.sqrt(),
+            ..sunny_16
+        };
+        let faster = Exposure {
+            iso: 200.0,
+            ..sunny_16
+        };
+        for exposure in [longer, wider, faster] {
+            assert_approx_eq!(exposure.scale() / base, 2.0);
+        }
+    }
+
+    #[test]
+    fn auto_exposure_maps_to_middle_gray() {
+        let mut film = Film::new((4, 2));
+        film.pixels.fill(Vec3::new(5.0, 5.0, 5.0));
+        film.samples.fill(1);
+        let scale = auto_exposure(&film);
+        assert_approx_eq!(scale * (5.0 + 1e-4), KEY_VALUE);
+
+        film.expose(scale);
+        assert_approx_eq!(luminance(film.mean(0)), 5.0 * scale);
+    }
+
+    #[test]
+    fn exposure_scales_any_camera() {
+        let camera: Camera = Equirectangular::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )
+        .into();
+        let exposure = Exposure {
+            f_number: 2.0,
+            shutter_time: 1.0 / 60.0,
+            iso: 400.0,
+        };
+        let exposed = camera.clone().with_exposure(exposure);
+        let (ray, weight) = exposed.get_weighted_ray((0.3, 0.6)).unwrap();
+        assert_eq!(ray.direction, camera.get_ray((0.3, 0.6)).unwrap().direction);
+        assert_approx_eq!(weight, exposure.scale());
+    }
+}
@@ -0,0 +1,149 @@
+use super::projection::{Frame, Projection, Shutter};
+use crate::{ray::Ray, vec3::Vec3};
+
+/// How the angle from the view direction maps to the distance from the image
+/// center.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle, as in dome masters.
+    Equidistant,
+    /// Equal solid angles cover equal image areas.
+    Equisolid,
+}
+
+/// Fisheye camera whose image circle fills the shorter side of the image.
+/// `field_of_view` is the angle across the circle in degrees, up to 360.
+/// Points outside the circle have no ray.
+#[derive(Clone, Debug)]
+pub struct Fisheye {
+    pub frame: Frame,
+    pub field_of_view: f64,
+    pub mapping: FisheyeMapping,
+    pub aspect_ratio: f64,
+    pub shutter: Shutter,
+}
+
+impl Fisheye {
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        up: Vec3,
+        field_of_view: f64,
+        mapping: FisheyeMapping,
+        aspect_ratio: f64,
+    ) -> Fisheye {
+        Fisheye {
+            frame: Frame::new(look_from, look_at, up),
+            field_of_view,
+            mapping,
+            aspect_ratio,
+            shutter: Shutter::default(),
+        }
+    }
+}
+
+impl Projection for Fisheye {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        let x = (2.0 * s - 1.0) * self.aspect_ratio.max(1.0);
+        let y = (2.0 * t - 1.0) / self.aspect_ratio.min(1.0);
+        let r = x.hypot(y);
+        if r > 1.0 {
+            return None;
+        }
+
+        let half_fov = self.field_of_view.to_radians() / 2.0;
+        let theta = match self.mapping {
+            FisheyeMapping::Equidistant => r * half_fov,
+            FisheyeMapping::Equisolid => 2.0 * (r * (half_fov / 2.0).sin()).asin(),
+        };
+        let phi = y.atan2(x);
+        let direction = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            -theta.cos(),
+        );
+        Some(self.frame.ray(direction, self.shutter.sample_time()))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        let offset = point - self.frame.origin;
+        // A point at the camera has no direction.
+        if offset.length_sq() == 0.0 {
+            return None;
+        }
+        let local = self.frame.to_local(offset.unitize());
+        let theta = (-local.z).clamp(-1.0, 1.0).acos();
+        let half_fov = self.field_of_view.to_radians() / 2.0;
+        if theta > half_fov {
+            return None;
+        }
+        let r = match self.mapping {
+            FisheyeMapping::Equidistant => theta / half_fov,
+            FisheyeMapping::Equisolid => (theta / 2.0).sin() / (half_fov / 2.0).sin(),
+        };
+        let phi = local.y.atan2(local.x);
+        let x = r * phi.cos() / self.aspect_ratio.max(1.0);
+        let y = r * phi.sin() * self.aspect_ratio.min(1.0);
+        Some(((x + 1.0) / 2.0, (y + 1.0) / 2.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn edge_of_circle_is_half_field_of_view() {
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let camera = Fisheye::new(
+                Vec3::origin(),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                180.0,
+                mapping,
+                2.0,
+            );
+            let direction = |st| camera.get_ray(st).unwrap().direction;
+            let center = direction((0.5, 0.5));
+            assert_approx_eq!((center - Vec3::new(0.0, 0.0, -1.0)).length(), 0.0);
+            let right = direction((0.75, 0.5));
+            assert_approx_eq!((right - Vec3::new(1.0, 0.0, 0.0)).length(), 0.0);
+            let top = direction((0.5, 1.0));
+            assert_approx_eq!((top - Vec3::new(0.0, 1.0, 0.0)).length(), 0.0);
+            assert!(camera.get_ray((0.9, 0.5)).is_none());
+
+            let (s, t) = camera.project(Vec3::new(1.0, 1.0, -1.0)).unwrap();
+            let ray = camera.get_ray((s, t)).unwrap();
+            assert_approx_eq!(
+                (ray.direction - Vec3::new(1.0, 1.0, -1.0).unitize()).length(),
+                0.0
+            );
+            assert_eq!(camera.project(Vec3::origin()), None);
+        }
+    }
+
+    #[test]
+    fn equisolid_compresses_edge() {
+        let camera = |mapping| {
+            Fisheye::new(
+                Vec3::origin(),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                180.0,
+                mapping,
+                1.0,
+            )
+        };
+        let angle = |mapping| {
+            let ray = camera(mapping).get_ray((0.75, 0.5)).unwrap();
+            (-ray.direction.z).acos().to_degrees()
+        };
+        assert_approx_eq!(angle(FisheyeMapping::Equidistant), 45.0);
+        assert_approx_eq!(
+            angle(FisheyeMapping::Equisolid),
+            2.0 * (0.5f64 * 0.5f64.sqrt()).asin().to_degrees()
+        );
+    }
+}
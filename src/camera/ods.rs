@@ -0,0 +1,133 @@
+use super::projection::{Frame, Projection, Shutter};
+use crate::{ray::Ray, vec3::Vec3};
+use std::f64::consts::{PI, TAU};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// One eye of an omni-directional stereo panorama, laid out like
+/// [`Equirectangular`](super::equirectangular::Equirectangular). Each ray
+/// leaves from the point on the circle of diameter `interocular` where the eye
+/// would be when turning the head towards it, so every direction is seen in
+/// stereo.
+#[derive(Clone, Debug)]
+pub struct Ods {
+    pub frame: Frame,
+    pub interocular: f64,
+    pub eye: Eye,
+    pub shutter: Shutter,
+}
+
+impl Ods {
+    pub fn new(look_from: Vec3, look_at: Vec3, up: Vec3, interocular: f64, eye: Eye) -> Ods {
+        Ods {
+            frame: Frame::new(look_from, look_at, up),
+            interocular,
+            eye,
+            shutter: Shutter::default(),
+        }
+    }
+}
+
+impl Projection for Ods {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        let longitude = (s - 0.5) * TAU;
+        let latitude = (t - 0.5) * PI;
+        let direction = Vec3::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+        let side = match self.eye {
+            Eye::Left => -0.5,
+            Eye::Right => 0.5,
+        };
+        let offset = side * self.interocular * Vec3::new(longitude.cos(), 0.0, longitude.sin());
+        let ray = self.frame.ray(direction, self.shutter.sample_time());
+        Some(Ray {
+            origin: ray.origin
+                + offset.x * self.frame.u
+                + offset.y * self.frame.v
+                + offset.z * self.frame.w,
+            ..ray
+        })
+    }
+
+    /// The ray through `point` leaves the viewing circle where the tangent
+    /// through the point touches it, on the side of the eye. Points inside the
+    /// circle are not seen by either eye.
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        let local = self.frame.to_local(point - self.frame.origin);
+        let radius = local.x.hypot(local.z);
+        let side = match self.eye {
+            Eye::Left => -0.5,
+            Eye::Right => 0.5,
+        };
+        let offset = side * self.interocular;
+        if radius <= offset.abs() {
+            return None;
+        }
+        // The offset from the center is perpendicular to the ray, so the ray
+        // turns away from the direction of the point by the angle the offset
+        // subtends.
+        let longitude = local.x.atan2(-local.z) - (offset / radius).asin();
+        let latitude = local.y.atan2((radius * radius - offset * offset).sqrt());
+        Some(((longitude / TAU + 0.5).rem_euclid(1.0), latitude / PI + 0.5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn eyes_sit_on_viewing_circle() {
+        let eye = |eye| {
+            Ods::new(
+                Vec3::origin(),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.064,
+                eye,
+            )
+        };
+        let (left, right) = (eye(Eye::Left), eye(Eye::Right));
+
+        // Looking forward the eyes are to the left and right.
+        let ray = right.get_ray((0.5, 0.5)).unwrap();
+        assert_approx_eq!((ray.origin - Vec3::new(0.032, 0.0, 0.0)).length(), 0.0);
+        let ray = left.get_ray((0.5, 0.5)).unwrap();
+        assert_approx_eq!((ray.origin - Vec3::new(-0.032, 0.0, 0.0)).length(), 0.0);
+
+        // Looking right the right eye is behind the center of the head.
+        let ray = right.get_ray((0.75, 0.5)).unwrap();
+        assert_approx_eq!((ray.origin - Vec3::new(0.0, 0.0, 0.032)).length(), 0.0);
+        assert_approx_eq!((ray.direction - Vec3::new(1.0, 0.0, 0.0)).length(), 0.0);
+        assert_approx_eq!(ray.origin.dot(ray.direction), 0.0);
+    }
+
+    #[test]
+    fn project_inverts_get_ray() {
+        for eye in [Eye::Left, Eye::Right] {
+            let camera = Ods::new(
+                Vec3::new(1.0, 2.0, 3.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                0.5,
+                eye,
+            );
+            for st in [(0.5, 0.5), (0.1, 0.3), (0.8, 0.9), (0.99, 0.05)] {
+                let ray = camera.get_ray(st).unwrap();
+                let (s, t) = camera.project(ray.evaluate(3.0)).unwrap();
+                assert_approx_eq!(s, st.0);
+                assert_approx_eq!(t, st.1);
+            }
+            assert_eq!(camera.project(Vec3::new(1.0, 5.0, 3.0)), None);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::{
+    perspective::project_onto_window,
+    projection::{Projection, Shutter},
+};
+use crate::{ray::Ray, vec3::Vec3};
+
+/// Camera with parallel rays leaving a `width` by `height` view rectangle
+/// centered on `look_from`, for views without perspective foreshortening.
+#[derive(Clone, Debug)]
+pub struct Orthographic {
+    pub lower_left_corner: Vec3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    pub direction: Vec3,
+    pub shutter: Shutter,
+}
+
+impl Orthographic {
+    pub fn new(look_from: Vec3, look_at: Vec3, up: Vec3, width: f64, height: f64) -> Orthographic {
+        let w = (look_from - look_at).unitize();
+        let u = Vec3::cross(up, w).unitize();
+        let v = Vec3::cross(w, u);
+
+        Orthographic {
+            lower_left_corner: look_from - width / 2.0 * u - height / 2.0 * v,
+            horizontal: width * u,
+            vertical: height * v,
+            direction: -w,
+            shutter: Shutter::default(),
+        }
+    }
+
+    /// Keeps the shutter open from `open` to `close`.
+    pub fn with_shutter(self, open: f64, close: f64) -> Orthographic {
+        Orthographic {
+            shutter: Shutter { open, close },
+            ..self
+        }
+    }
+}
+
+impl Projection for Orthographic {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        Some(Ray::new(
+            self.lower_left_corner + s * self.horizontal + t * self.vertical,
+            self.direction,
+            self.shutter.sample_time(),
+        ))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        // Tracing back towards the view rectangle only reaches it from points in
+        // front of the camera.
+        project_onto_window(
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            point,
+            -self.direction,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn rays_are_parallel() {
+        let camera = Orthographic::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::origin(),
+            Vec3::new(0.0, 1.0, 0.0),
+            4.0,
+            2.0,
+        );
+        let corner = camera.get_ray((0.0, 0.0)).unwrap();
+        let center = camera.get_ray((0.5, 0.5)).unwrap();
+        let top_right = camera.get_ray((1.0, 1.0)).unwrap();
+
+        assert_approx_eq!((corner.origin - Vec3::new(-2.0, -1.0, 5.0)).length(), 0.0);
+        assert_approx_eq!((center.origin - Vec3::new(0.0, 0.0, 5.0)).length(), 0.0);
+        assert_approx_eq!((top_right.origin - Vec3::new(2.0, 1.0, 5.0)).length(), 0.0);
+        for ray in [corner, center, top_right] {
+            assert_approx_eq!((ray.direction - Vec3::new(0.0, 0.0, -1.0)).length(), 0.0);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use super::{
+    aperture::Aperture,
+    exposure::Exposure,
+    projection::{Camera, Projection, Shutter},
+};
+use crate::{ray::Ray, shape::hittable::Hittable, vec3::Vec3};
+
+/// Thin lens camera with a perspective projection.
+#[derive(Clone, Debug)]
+pub struct Perspective {
+    pub origin: Vec3,
+    pub lower_left_corner: Vec3,
+    pub horizontal: Vec3,
+    pub vertical: Vec3,
+    pub lens_radius: f64,
+    pub aperture: Aperture,
+    pub shutter: Shutter,
+    u: Vec3,
+    v: Vec3,
+}
+
+impl Perspective {
+    pub fn new(
+        look_from: Vec3,
+        look_at: Vec3,
+        up: Vec3,
+        vertical_fov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_distance: f64,
+    ) -> Perspective {
+        let theta = vertical_fov.to_radians();
+        let half_height = (theta / 2.0).tan();
+        let half_width = half_height * aspect_ratio;
+
+        let w = (look_from - look_at).unitize();
+        let u = Vec3::cross(up, w).unitize();
+        let v = Vec3::cross(w, u);
+
+        Perspective {
+            origin: look_from,
+            lower_left_corner: look_from
+                - half_width * focus_distance * u
+                - half_height * focus_distance * v
+                - focus_distance * w,
+            horizontal: 2.0 * half_width * focus_distance * u,
+            vertical: 2.0 * half_height * focus_distance * v,
+            lens_radius: aperture / 2.0,
+            aperture: Aperture::Circle,
+            shutter: Shutter::default(),
+            u,
+            v,
+        }
+    }
+
+    /// Gives the lens opening the shape of `aperture`, scaled by the lens radius.
+    pub fn with_aperture(self, aperture: Aperture) -> Perspective {
+        Perspective { aperture, ..self }
+    }
+
+    /// Camera scaling radiance by the exposure of the given settings, see
+    /// [`Camera::with_exposure`]. The lens is opened to the f-number of the
+    /// exposure for a lens with `focal_length` in scene units, replacing the
+    /// aperture the camera was created with, so the depth of field matches the
+    /// brightness.
+    pub fn with_exposure(self, exposure: Exposure, focal_length: f64) -> Camera {
+        let lens_radius = aperture_from_f_number(focal_length, exposure.f_number) / 2.0;
+        Camera::from(Perspective {
+            lens_radius,
+            ..self
+        })
+        .with_exposure(exposure)
+    }
+
+    /// Keeps the shutter open from `open` to `close`.
+    pub fn with_shutter(self, open: f64, close: f64) -> Perspective {
+        Perspective {
+            shutter: Shutter { open, close },
+            ..self
+        }
+    }
+
+    /// Distance from the lens to the plane in focus, along the view direction.
+    pub fn focus_distance(&self) -> f64 {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        (center - self.origin).length()
+    }
+
+    /// Moves the plane in focus to `focus_distance` keeping the field of view.
+    pub fn with_focus_distance(self, focus_distance: f64) -> Perspective {
+        let k = focus_distance / self.focus_distance();
+        Perspective {
+            lower_left_corner: self.origin + k * (self.lower_left_corner - self.origin),
+            horizontal: k * self.horizontal,
+            vertical: k * self.vertical,
+            ..self
+        }
+    }
+
+    /// Focuses on the closest object in `scene` seen through the image point
+    /// `st`. The focus is left unchanged if nothing is there.
+    pub fn autofocus<T: Hittable>(self, scene: &T, st: (f64, f64)) -> Perspective {
+        let (s, t) = st;
+        let target = self.lower_left_corner + s * self.horizontal + t * self.vertical;
+        let ray = Ray::new(self.origin, target - self.origin, self.shutter.open);
+        match scene.hit(&ray, 0.0001..f64::INFINITY) {
+            Some(rec) => {
+                // The plane in focus is perpendicular to the view direction, so
+                // an off center point is focused on by its depth.
+                let forward = Vec3::cross(self.v, self.u);
+                let depth = rec.t * ray.direction.dot(forward);
+                self.with_focus_distance(depth)
+            }
+            None => self,
+        }
+    }
+}
+
+/// Diameter of the entrance pupil of a lens with the given focal length, in the
+/// same units, stopped down to `f_number`.
+pub fn aperture_from_f_number(focal_length: f64, f_number: f64) -> f64 {
+    focal_length / f_number
+}
+
+impl Projection for Perspective {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        let rd = self.lens_radius * self.aperture.sample();
+        let offset = self.u * rd.x + self.v * rd.y;
+        Some(Ray::new(
+            self.origin + offset,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+            self.shutter.sample_time(),
+        ))
+    }
+
+    fn chief_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (s, t) = st;
+        Some(Ray::new(
+            self.origin,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
+            self.shutter.middle(),
+        ))
+    }
+
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        project_onto_window(
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            self.origin,
+            point - self.origin,
+        )
+    }
+}
+
+/// Image point where the ray from `origin` along `direction` crosses the window
+/// spanned by `horizontal` and `vertical` from its lower left corner, if the
+/// ray crosses the window plane.
+pub(crate) fn project_onto_window(
+    lower_left_corner: Vec3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<(f64, f64)> {
+    let normal = Vec3::cross(horizontal, vertical);
+    let t = (lower_left_corner - origin).dot(normal) / direction.dot(normal);
+    if !t.is_finite() || t <= 0.0 {
+        return None;
+    }
+    let q = origin + t * direction - lower_left_corner;
+    Some((
+        q.dot(horizontal) / horizontal.length_sq(),
+        q.dot(vertical) / vertical.length_sq(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{lambertian::Lambertian, scatterable::Material};
+    use crate::shape::{hittable::Shape, sphere::Sphere};
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn project_inverts_get_ray() {
+        let camera = Perspective::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            30.0,
+            2.0,
+            0.0,
+            4.0,
+        );
+        for st in [(0.5, 0.5), (0.1, 0.8), (0.95, 0.05)] {
+            let ray = camera.get_ray(st).unwrap();
+            let (s, t) = camera.project(ray.origin + 7.0 * ray.direction).unwrap();
+            assert_approx_eq!(s, st.0);
+            assert_approx_eq!(t, st.1);
+        }
+        let behind = camera.origin + (camera.origin - Vec3::new(0.0, 0.0, -1.0));
+        assert!(camera.project(behind).is_none());
+    }
+
+    #[test]
+    fn autofocus_on_hit() {
+        let camera = Perspective::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            30.0,
+            2.0,
+            0.1,
+            1.0,
+        );
+        let scene: Vec<Shape> = vec![Shape::Sph(Sphere::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            1.0,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+            }),
+        ))];
+        let before = camera.get_ray((0.3, 0.7)).unwrap();
+
+        let focused = camera.autofocus(&scene, (0.5, 0.5));
+        assert_approx_eq!(focused.focus_distance(), 4.0);
+        let after = focused.get_ray((0.3, 0.7)).unwrap();
+        assert_approx_eq!(before.direction.dot(after.direction), 1.0, 1e-2);
+
+        let unchanged = focused.autofocus(&scene, (0.0, 0.0));
+        assert_approx_eq!(unchanged.focus_distance(), 4.0);
+    }
+
+    #[test]
+    fn f_number_sets_aperture() {
+        assert_approx_eq!(aperture_from_f_number(0.05, 2.0), 0.025);
+    }
+}
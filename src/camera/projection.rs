@@ -0,0 +1,275 @@
+use super::{
+    cube_map::CubeMap,
+    equirectangular::Equirectangular,
+    exposure::{Exposed, Exposure},
+    fisheye::Fisheye,
+    ods::Ods,
+    orthographic::Orthographic,
+    perspective::Perspective,
+    realistic::Realistic,
+    stereo::Stereo,
+};
+use crate::{
+    random::{self, PrimarySampler},
+    ray::Ray,
+    shape::hittable::{HitRecord, Hittable, Shape},
+    vec3::Vec3,
+};
+use enum_dispatch::enum_dispatch;
+
+#[enum_dispatch(Camera)]
+pub trait Projection: std::fmt::Debug {
+    /// Ray through the point `st` of the image, with `(0, 0)` the lower left and
+    /// `(1, 1)` the upper right corner. Points outside the area covered by the
+    /// projection have no ray.
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray>;
+
+    /// Ray through the point `st` from the center of the lens at the middle of
+    /// the shutter interval. Unlike [`Projection::get_ray`] it takes no random
+    /// samples, so the same point always gives the same ray.
+    ///
+    /// By default this is [`Projection::get_ray`] with every random number in
+    /// the middle of its range, which suits projections whose only random part
+    /// is the shutter time.
+    fn chief_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        random::with_primary_sampler(&mut Midpoint, || self.get_ray(st))
+    }
+
+    /// Like [`Projection::get_ray`], together with the factor the radiance
+    /// along the ray is scaled by, such as for vignetting.
+    fn get_weighted_ray(&self, st: (f64, f64)) -> Option<(Ray, f64)> {
+        self.get_ray(st).map(|ray| (ray, 1.0))
+    }
+
+    /// Image point `st` whose ray passes through `point`, the inverse of
+    /// [`Projection::get_ray`] for a pinhole at the lens center. `None` if the
+    /// camera can not see the point.
+    fn project(&self, point: Vec3) -> Option<(f64, f64)>;
+}
+
+/// Sampler that always answers the middle of the unit interval.
+#[derive(Default)]
+struct Midpoint;
+
+impl PrimarySampler for Midpoint {
+    fn next_sample(&mut self) -> f64 {
+        0.5
+    }
+}
+
+#[enum_dispatch]
+#[derive(Clone, Debug)]
+pub enum Camera {
+    Perspective,
+    Orthographic,
+    Equirectangular,
+    CubeMap,
+    Fisheye,
+    Realistic,
+    Ods,
+    Stereo,
+    Exposed,
+}
+
+/// World point projected onto the image, in raster coordinates with the origin
+/// at the upper left corner and y pointing down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Raster {
+    pub x: f64,
+    pub y: f64,
+    /// Whether the point is inside the image.
+    pub visible: bool,
+}
+
+/// Object under a pixel, found by [`Camera::pick`].
+pub struct Pick<'a> {
+    /// Index of the object in the picked slice.
+    pub object: usize,
+    pub record: HitRecord<'a>,
+}
+
+impl Camera {
+    /// Scales the radiance seen by the camera by the exposure of the given
+    /// settings, which does not change its rays.
+    pub fn with_exposure(self, exposure: Exposure) -> Camera {
+        Camera::Exposed(Exposed {
+            camera: Box::new(self),
+            exposure,
+        })
+    }
+
+    /// Raster position of `point` in an image of the given `resolution`.
+    pub fn world_to_raster(&self, point: Vec3, resolution: (u32, u32)) -> Option<Raster> {
+        let (s, t) = self.project(point)?;
+        let (width, height) = (resolution.0 as f64, resolution.1 as f64);
+        let (x, y) = (s * width, (1.0 - t) * height);
+        Some(Raster {
+            x,
+            y,
+            visible: (0.0..width).contains(&x) && (0.0..height).contains(&y),
+        })
+    }
+
+    /// Closest of `objects` seen through the center of `pixel`, counted from the
+    /// upper left corner of an image of the given `resolution`.
+    pub fn pick<'a>(
+        &self,
+        objects: &'a [Shape],
+        resolution: (u32, u32),
+        pixel: (u32, u32),
+    ) -> Option<Pick<'a>> {
+        let s = (pixel.0 as f64 + 0.5) / resolution.0 as f64;
+        let t = 1.0 - (pixel.1 as f64 + 0.5) / resolution.1 as f64;
+        let ray = self.chief_ray((s, t))?;
+        let mut closest: Option<Pick> = None;
+        for (object, shape) in objects.iter().enumerate() {
+            let t_max = closest.as_ref().map_or(f64::INFINITY, |p| p.record.t);
+            if let Some(record) = shape.hit(&ray, 0.0001..t_max) {
+                closest = Some(Pick { object, record });
+            }
+        }
+        closest
+    }
+}
+
+/// Position and orientation of a camera, looking along `-w` with `v` up and `u`
+/// to the right.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Frame {
+    pub fn new(look_from: Vec3, look_at: Vec3, up: Vec3) -> Frame {
+        let w = (look_from - look_at).unitize();
+        let u = Vec3::cross(up, w).unitize();
+        let v = Vec3::cross(w, u);
+        Frame {
+            origin: look_from,
+            u,
+            v,
+            w,
+        }
+    }
+
+    /// Ray from the camera position along `local`, given in the `u`, `v`, `w`
+    /// basis of the frame.
+    pub fn ray(&self, local: Vec3, time: f64) -> Ray {
+        Ray::new(
+            self.origin,
+            local.x * self.u + local.y * self.v + local.z * self.w,
+            time,
+        )
+    }
+
+    /// World direction `world` in the `u`, `v`, `w` basis of the frame.
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(self.u), world.dot(self.v), world.dot(self.w))
+    }
+}
+
+/// Interval during which the shutter is open, rays are given a time uniformly
+/// distributed in it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+}
+
+impl Shutter {
+    pub fn middle(&self) -> f64 {
+        match self.close > self.open {
+            true => (self.open + self.close) / 2.0,
+            false => self.open,
+        }
+    }
+
+    pub fn sample_time(&self) -> f64 {
+        match self.close > self.open {
+            true => random::uniform_range(self.open..self.close),
+            false => self.open,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::perspective::Perspective;
+    use crate::create_scene::create_5_sphere_scene;
+    use crate::material::{lambertian::Lambertian, scatterable::Material};
+    use crate::shape::sphere::Sphere;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn raster_and_pick_agree() {
+        let resolution = (200, 100);
+        let (camera, world) = create_5_sphere_scene(200.0, 100.0);
+
+        let center = Vec3::new(0.0, 0.0, -1.0);
+        let raster = camera.world_to_raster(center, resolution).unwrap();
+        assert!(raster.visible);
+        assert_approx_eq!(raster.x, 100.0);
+        assert_approx_eq!(raster.y, 50.0);
+
+        let pick = camera
+            .pick(&world, resolution, (raster.x as u32, raster.y as u32))
+            .unwrap();
+        assert_eq!(pick.object, 0);
+        assert_approx_eq!((pick.record.point - center).length(), 0.5, 1e-2);
+
+        let off_screen = camera.world_to_raster(Vec3::new(-5.0, 0.0, 3.0), resolution);
+        assert!(!off_screen.unwrap().visible);
+    }
+
+    #[test]
+    fn pick_ignores_aperture_and_shutter() {
+        let camera: Camera = Perspective::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            2.0,
+            1.0,
+        )
+        .with_shutter(0.0, 1.0)
+        .into();
+        let world = [Shape::Sph(Sphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            0.5,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+            }),
+        ))];
+
+        for _ in 0..16 {
+            let pick = camera.pick(&world, (101, 101), (50, 50)).unwrap();
+            assert_eq!(pick.record.point, Vec3::new(0.0, 0.0, -1.5));
+        }
+    }
+
+    #[test]
+    fn default_chief_ray_is_at_middle_of_shutter() {
+        let camera = Equirectangular {
+            shutter: Shutter {
+                open: 1.0,
+                close: 3.0,
+            },
+            ..Equirectangular::new(
+                Vec3::origin(),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            )
+        };
+        let ray = camera.chief_ray((0.25, 0.5)).unwrap();
+        assert_eq!(ray.time, 2.0);
+        assert_eq!(
+            ray.direction,
+            camera.get_ray((0.25, 0.5)).unwrap().direction
+        );
+    }
+}
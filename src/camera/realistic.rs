@@ -0,0 +1,560 @@
+use super::projection::{Frame, Projection, Shutter};
+use crate::{random, ray::Ray, utility::lerp, vec3::Vec3};
+use rayon::prelude::*;
+use std::{fs, io, path::Path};
+
+/// One spherical interface of a lens system, or the aperture stop if the
+/// curvature radius is zero. Lengths are in scene units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LensElement {
+    pub curvature_radius: f64,
+    /// Distance along the axis to the next interface, or to the film for the
+    /// last one.
+    pub thickness: f64,
+    /// Index of refraction of the medium behind the interface, zero for air.
+    pub ior: f64,
+    pub aperture_radius: f64,
+}
+
+/// Lens system listed from the element facing the scene to the one facing the
+/// film.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lens {
+    pub elements: Vec<LensElement>,
+}
+
+impl Lens {
+    /// Reads a lens prescription in the format used by pbrt, one interface per
+    /// line given as curvature radius, thickness, index of refraction and
+    /// aperture diameter in millimeters. Lines starting with `#` are comments.
+    pub fn parse(prescription: &str) -> io::Result<Lens> {
+        let elements = prescription
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let values = line
+                    .split_whitespace()
+                    .map(|v| v.parse::<f64>().map_err(|_| invalid(line)))
+                    .collect::<io::Result<Vec<f64>>>()?;
+                let [radius, thickness, ior, diameter] = values[..] else {
+                    return Err(invalid(line));
+                };
+                Ok(LensElement {
+                    curvature_radius: radius * 0.001,
+                    thickness: thickness * 0.001,
+                    ior,
+                    aperture_radius: diameter * 0.001 / 2.0,
+                })
+            })
+            .collect::<io::Result<Vec<LensElement>>>()?;
+        match elements.is_empty() {
+            true => Err(invalid("no lens elements")),
+            false => Ok(Lens { elements }),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Lens> {
+        Lens::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Stops the aperture down to `diameter`, it can not be opened wider than
+    /// the prescription allows.
+    pub fn with_aperture_diameter(mut self, diameter: f64) -> Lens {
+        for element in self.elements.iter_mut() {
+            if element.curvature_radius == 0.0 {
+                element.aperture_radius = element.aperture_radius.min(diameter / 2.0);
+            }
+        }
+        self
+    }
+
+    fn front_z(&self) -> f64 {
+        self.elements.iter().map(|e| e.thickness).sum()
+    }
+
+    fn rear_z(&self) -> f64 {
+        self.elements.last().unwrap().thickness
+    }
+
+    fn rear_radius(&self) -> f64 {
+        self.elements.last().unwrap().aperture_radius
+    }
+
+    /// Traces a ray leaving the film at `z = 0` towards the lens at positive z
+    /// through the lens system, returning the ray leaving the front element.
+    fn trace_from_film(&self, origin: Vec3, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        // Lens space mirrors camera space so that the lens lies at negative z.
+        let mut origin = Vec3::new(origin.x, origin.y, -origin.z);
+        let mut direction = Vec3::new(direction.x, direction.y, -direction.z).unitize();
+        let mut element_z = 0.0;
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            element_z -= element.thickness;
+            let eta_i = match element.ior != 0.0 {
+                true => element.ior,
+                false => 1.0,
+            };
+            let eta_t = match i > 0 && self.elements[i - 1].ior != 0.0 {
+                true => self.elements[i - 1].ior,
+                false => 1.0,
+            };
+            (origin, direction) =
+                self.interface(element, element_z, eta_i, eta_t, origin, direction)?;
+        }
+        Some((
+            Vec3::new(origin.x, origin.y, -origin.z),
+            Vec3::new(direction.x, direction.y, -direction.z),
+        ))
+    }
+
+    /// Traces a ray from the scene at positive z through the lens system,
+    /// returning the ray leaving the rear element towards the film.
+    fn trace_from_scene(&self, origin: Vec3, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        let mut origin = Vec3::new(origin.x, origin.y, -origin.z);
+        let mut direction = Vec3::new(direction.x, direction.y, -direction.z).unitize();
+        let mut element_z = -self.front_z();
+        for (i, element) in self.elements.iter().enumerate() {
+            let eta_i = match i > 0 && self.elements[i - 1].ior != 0.0 {
+                true => self.elements[i - 1].ior,
+                false => 1.0,
+            };
+            let eta_t = match element.ior != 0.0 {
+                true => element.ior,
+                false => 1.0,
+            };
+            (origin, direction) =
+                self.interface(element, element_z, eta_i, eta_t, origin, direction)?;
+            element_z += element.thickness;
+        }
+        Some((
+            Vec3::new(origin.x, origin.y, -origin.z),
+            Vec3::new(direction.x, direction.y, -direction.z),
+        ))
+    }
+
+    /// Moves a ray in lens space across the interface at `element_z`, or returns
+    /// `None` if it misses the element, is blocked by its aperture or is
+    /// totally internally reflected.
+    fn interface(
+        &self,
+        element: &LensElement,
+        element_z: f64,
+        eta_i: f64,
+        eta_t: f64,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Option<(Vec3, Vec3)> {
+        if element.curvature_radius == 0.0 {
+            let t = (element_z - origin.z) / direction.z;
+            if direction.z == 0.0 || t < 0.0 {
+                return None;
+            }
+            let hit = origin + t * direction;
+            return match hit.x * hit.x + hit.y * hit.y
+                <= element.aperture_radius * element.aperture_radius
+            {
+                true => Some((hit, direction)),
+                false => None,
+            };
+        }
+
+        let radius = element.curvature_radius;
+        let center = Vec3::new(0.0, 0.0, element_z + radius);
+        let oc = origin - center;
+        let half_b = oc.dot(direction);
+        let c = oc.length_sq() - radius * radius;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let (t0, t1) = (-half_b - discriminant.sqrt(), -half_b + discriminant.sqrt());
+        // The interface is the half of the sphere facing the vertex at
+        // `element_z`.
+        let t = match (direction.z > 0.0) ^ (radius < 0.0) {
+            true => t0.min(t1),
+            false => t0.max(t1),
+        };
+        if t < 0.0 {
+            return None;
+        }
+        let hit = origin + t * direction;
+        if hit.x * hit.x + hit.y * hit.y > element.aperture_radius * element.aperture_radius {
+            return None;
+        }
+        let mut normal = (hit - center).unitize();
+        if normal.dot(direction) > 0.0 {
+            normal = -normal;
+        }
+        let refracted = direction.refract(normal, eta_i / eta_t)?;
+        Some((hit, refracted.unitize()))
+    }
+
+    /// Axial positions of the principal plane and focal point of the lens, for a
+    /// ray entering parallel to the axis and the ray leaving it.
+    fn cardinal_points(entering: (Vec3, Vec3), leaving: (Vec3, Vec3)) -> (f64, f64) {
+        let (origin, direction) = leaving;
+        let t_focus = -origin.x / direction.x;
+        let t_principal = (entering.0.x - origin.x) / direction.x;
+        (
+            -(origin.z + t_principal * direction.z),
+            -(origin.z + t_focus * direction.z),
+        )
+    }
+
+    /// Principal planes and focal points on the film side and the scene side of
+    /// the lens, used to approximate it as a thick lens.
+    fn thick_lens_approximation(&self, height: f64) -> io::Result<([f64; 2], [f64; 2])> {
+        let scene_ray = (
+            Vec3::new(height, 0.0, self.front_z() + 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        let film_ray = self
+            .trace_from_scene(scene_ray.0, scene_ray.1)
+            .ok_or_else(|| invalid("paraxial ray does not pass through the lens"))?;
+        let (p0, f0) = Lens::cardinal_points(scene_ray, film_ray);
+
+        let film_ray = (
+            Vec3::new(height, 0.0, self.rear_z() - 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        let scene_ray = self
+            .trace_from_film(film_ray.0, film_ray.1)
+            .ok_or_else(|| invalid("paraxial ray does not pass through the lens"))?;
+        let (p1, f1) = Lens::cardinal_points(film_ray, scene_ray);
+        Ok(([p0, p1], [f0, f1]))
+    }
+
+    /// Moves the lens along the axis so that objects at `focus_distance` from
+    /// the film are in focus, or fails if the lens can not focus that close.
+    fn focus(&mut self, focus_distance: f64, height: f64) -> io::Result<()> {
+        let (pz, fz) = self.thick_lens_approximation(height)?;
+        let f = fz[0] - pz[0];
+        let z = -focus_distance;
+        let c = (pz[1] - z - pz[0]) * (pz[1] - z - 4.0 * f - pz[0]);
+        if c < 0.0 {
+            return Err(invalid(&format!("can not focus at {}", focus_distance)));
+        }
+        let delta = 0.5 * (pz[1] - z + pz[0] - c.sqrt());
+        self.elements.last_mut().unwrap().thickness += delta;
+        Ok(())
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid lens prescription: {}", msg),
+    )
+}
+
+/// Axis aligned rectangle on the plane of the rear lens element.
+#[derive(Clone, Copy, Debug)]
+struct PupilBounds {
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl PupilBounds {
+    fn area(&self) -> f64 {
+        (self.max.0 - self.min.0).max(0.0) * (self.max.1 - self.min.1).max(0.0)
+    }
+}
+
+/// Camera tracing rays through the elements of a real lens, as in pbrt's
+/// realistic camera. The film is a `film_diagonal` sized rectangle behind the
+/// lens, which is moved to focus at `focus_distance`. Rays are sampled towards
+/// bounds of the exit pupil precomputed for rings of the film, so that few of
+/// them are blocked inside the lens.
+#[derive(Clone, Debug)]
+pub struct Realistic {
+    pub frame: Frame,
+    pub lens: Lens,
+    pub film_width: f64,
+    pub film_height: f64,
+    pub shutter: Shutter,
+    exit_pupil_bounds: Vec<PupilBounds>,
+    /// Axial positions of the principal planes on the film side and the scene
+    /// side of the focused lens, in lens space.
+    principal_planes: [f64; 2],
+}
+
+impl Realistic {
+    const PUPIL_RINGS: usize = 64;
+    const PUPIL_SAMPLES: usize = 128;
+
+    /// Fails if the lens can not focus at `focus_distance`.
+    pub fn new(
+        frame: Frame,
+        lens: Lens,
+        focus_distance: f64,
+        film_diagonal: f64,
+        aspect_ratio: f64,
+    ) -> io::Result<Realistic> {
+        let mut lens = lens;
+        lens.focus(focus_distance, 0.001 * film_diagonal)?;
+        let (principal_planes, _) = lens.thick_lens_approximation(0.001 * film_diagonal)?;
+        let film_width = film_diagonal / (1.0 + 1.0 / (aspect_ratio * aspect_ratio)).sqrt();
+        let film_height = film_width / aspect_ratio;
+
+        let ring_width = film_diagonal / 2.0 / Realistic::PUPIL_RINGS as f64;
+        let exit_pupil_bounds = (0..Realistic::PUPIL_RINGS)
+            .into_par_iter()
+            .map(|i| {
+                Realistic::bound_exit_pupil(
+                    &lens,
+                    i as f64 * ring_width,
+                    (i + 1) as f64 * ring_width,
+                )
+            })
+            .collect();
+
+        Ok(Realistic {
+            frame,
+            lens,
+            film_width,
+            film_height,
+            shutter: Shutter::default(),
+            exit_pupil_bounds,
+            principal_planes,
+        })
+    }
+
+    /// Keeps the shutter open from `open` to `close`.
+    pub fn with_shutter(self, open: f64, close: f64) -> Realistic {
+        Realistic {
+            shutter: Shutter { open, close },
+            ..self
+        }
+    }
+
+    /// Bounds the points on the rear element through which rays from film points
+    /// between `r0` and `r1` along the x axis leave the lens.
+    fn bound_exit_pupil(lens: &Lens, r0: f64, r1: f64) -> PupilBounds {
+        let n = Realistic::PUPIL_SAMPLES;
+        let extent = 1.5 * lens.rear_radius();
+        let rear_z = lens.rear_z();
+        let mut bounds: Option<PupilBounds> = None;
+        for i in 0..n * n {
+            let film = Vec3::new(lerp(r0, r1, (i as f64 + 0.5) / (n * n) as f64), 0.0, 0.0);
+            let x = lerp(-extent, extent, ((i % n) as f64 + 0.5) / n as f64);
+            let y = lerp(-extent, extent, ((i / n) as f64 + 0.5) / n as f64);
+            let rear = Vec3::new(x, y, rear_z);
+            if lens.trace_from_film(film, rear - film).is_some() {
+                bounds = Some(match bounds {
+                    Some(b) => PupilBounds {
+                        min: (b.min.0.min(x), b.min.1.min(y)),
+                        max: (b.max.0.max(x), b.max.1.max(y)),
+                    },
+                    None => PupilBounds {
+                        min: (x, y),
+                        max: (x, y),
+                    },
+                });
+            }
+        }
+        // Grow the bounds by a grid cell to cover rays between the samples.
+        let cell = 2.0 * extent / n as f64;
+        match bounds {
+            Some(b) => PupilBounds {
+                min: (b.min.0 - cell, b.min.1 - cell),
+                max: (b.max.0 + cell, b.max.1 + cell),
+            },
+            None => PupilBounds {
+                min: (-extent, -extent),
+                max: (extent, extent),
+            },
+        }
+    }
+
+    /// Ray at `time` through the image point `st` towards the point
+    /// `lens_sample` of the exit pupil bounds, in the unit square.
+    fn generate_ray(
+        &self,
+        st: (f64, f64),
+        lens_sample: (f64, f64),
+        time: f64,
+    ) -> Option<(Ray, f64)> {
+        let (s, t) = st;
+        // The lens flips the image, so the upper right of the image is at the
+        // lower left of the film.
+        let film = Vec3::new(
+            (0.5 - s) * self.film_width,
+            (0.5 - t) * self.film_height,
+            0.0,
+        );
+
+        let film_radius = film.x.hypot(film.y);
+        let ring = film_radius / (self.film_width.hypot(self.film_height) / 2.0)
+            * self.exit_pupil_bounds.len() as f64;
+        let bounds = self.exit_pupil_bounds[(ring as usize).min(self.exit_pupil_bounds.len() - 1)];
+        let pupil = (
+            lerp(bounds.min.0, bounds.max.0, lens_sample.0),
+            lerp(bounds.min.1, bounds.max.1, lens_sample.1),
+        );
+        let (sin, cos) = match film_radius > 0.0 {
+            true => (film.y / film_radius, film.x / film_radius),
+            false => (0.0, 1.0),
+        };
+        let rear = Vec3::new(
+            cos * pupil.0 - sin * pupil.1,
+            sin * pupil.0 + cos * pupil.1,
+            self.lens.rear_z(),
+        );
+
+        let film_direction = (rear - film).unitize();
+        let (origin, direction) = self.lens.trace_from_film(film, film_direction)?;
+
+        // Camera space looks along positive z, with x to the right and y up.
+        let to_world = |p: Vec3| p.x * self.frame.u + p.y * self.frame.v - p.z * self.frame.w;
+        let ray = Ray::new(
+            self.frame.origin + to_world(origin),
+            to_world(direction),
+            time,
+        );
+        let cos2 = film_direction.z * film_direction.z;
+        let weight = cos2 * cos2 * bounds.area() / self.exit_pupil_bounds[0].area();
+        Some((ray, weight))
+    }
+}
+
+impl Projection for Realistic {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        self.get_weighted_ray(st).map(|(ray, _)| ray)
+    }
+
+    /// Ray through the middle of the exit pupil bounds, which may be blocked
+    /// by the lens far off axis.
+    fn chief_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        self.generate_ray(st, (0.5, 0.5), self.shutter.middle())
+            .map(|(ray, _)| ray)
+    }
+
+    fn get_weighted_ray(&self, st: (f64, f64)) -> Option<(Ray, f64)> {
+        let lens_sample = (random::uniform(), random::uniform());
+        self.generate_ray(st, lens_sample, self.shutter.sample_time())
+    }
+
+    /// Uses the thick lens approximation of the lens: the ray towards the
+    /// scene side principal point leaves the film side one in the same
+    /// direction. Away from the axis this drifts from the traced rays by the
+    /// distortion of the lens, and points behind the lens are not seen.
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        // Lens space has the film at `z = 0` and the scene at negative z, like
+        // the frame.
+        let local = self.frame.to_local(point - self.frame.origin);
+        let [film_side, scene_side] = self.principal_planes;
+        if local.z >= scene_side {
+            return None;
+        }
+        let scale = film_side / (scene_side - local.z);
+        let film = (scale * local.x, scale * local.y);
+        Some((
+            0.5 - film.0 / self.film_width,
+            0.5 - film.1 / self.film_height,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(focus_distance: f64) -> Realistic {
+        let lens = Lens::load("./lenses/dgauss.50mm.dat").unwrap();
+        let frame = Frame::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        Realistic::new(frame, lens, focus_distance, 0.035, 1.5).unwrap()
+    }
+
+    #[test]
+    fn parse_prescription() {
+        let lens = Lens::parse("# radius thickness ior aperture\n10 2 1.5 8\n\n0 1 0 4\n").unwrap();
+        assert_eq!(lens.elements.len(), 2);
+        assert_eq!(lens.elements[0].aperture_radius, 0.004);
+        assert_eq!(lens.elements[1].curvature_radius, 0.0);
+        assert!(Lens::parse("10 2 1.5\n").is_err());
+        assert!(Lens::parse("# empty\n").is_err());
+
+        let stopped = lens.with_aperture_diameter(0.002);
+        assert_eq!(stopped.elements[1].aperture_radius, 0.001);
+    }
+
+    #[test]
+    fn traces_are_reversible() {
+        // Biconvex lens with the air behind it written as zero.
+        let lens = Lens::parse("20 3 1.5 10\n-20 40 0 10\n").unwrap();
+        let film = Vec3::new(0.001, -0.0005, 0.0);
+        let rear = Vec3::new(0.0005, 0.001, lens.rear_z());
+        let (origin, direction) = lens.trace_from_film(film, rear - film).unwrap();
+        let start = origin + 0.01 * direction;
+        let (back, back_direction) = lens.trace_from_scene(start, -direction).unwrap();
+        let t = -back.z / back_direction.z;
+        assert!(((back + t * back_direction) - film).length() < 1e-9);
+    }
+
+    #[test]
+    fn focusing_too_close_fails() {
+        let lens = Lens::load("./lenses/dgauss.50mm.dat").unwrap();
+        let frame = Frame::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert!(Realistic::new(frame, lens, 0.05, 0.035, 1.5).is_err());
+    }
+
+    #[test]
+    fn focuses_on_axis() {
+        let camera = camera(2.0);
+        let target = Vec3::new(0.0, 0.0, -2.0);
+        let mut rays = 0;
+        for i in 0..8 {
+            for j in 0..8 {
+                let lens_sample = ((i as f64 + 0.5) / 8.0, (j as f64 + 0.5) / 8.0);
+                if let Some((ray, _)) = camera.generate_ray((0.5, 0.5), lens_sample, 0.0) {
+                    rays += 1;
+                    let to_target = target - ray.origin;
+                    let miss = (to_target - to_target.dot(ray.direction) * ray.direction).length();
+                    assert!(miss < 2e-3, "ray misses focus point by {}", miss);
+                }
+            }
+        }
+        assert!(rays > 16);
+    }
+
+    #[test]
+    fn corners_are_vignetted() {
+        let camera = camera(10.0);
+        let throughput = |st| {
+            (0..16 * 16)
+                .filter_map(|i| {
+                    let lens_sample = (
+                        ((i % 16) as f64 + 0.5) / 16.0,
+                        ((i / 16) as f64 + 0.5) / 16.0,
+                    );
+                    camera.generate_ray(st, lens_sample, 0.0)
+                })
+                .map(|(_, weight)| weight)
+                .sum::<f64>()
+        };
+        assert!(throughput((0.0, 0.0)) < throughput((0.5, 0.5)));
+        assert!(throughput((0.5, 0.5)) > 0.0);
+    }
+
+    #[test]
+    fn project_follows_chief_rays() {
+        let camera = camera(2.0);
+        // The thick lens approximation ignores distortion, which grows away from
+        // the center of the image.
+        for st in [(0.5, 0.5), (0.45, 0.55), (0.6, 0.4), (0.2, 0.8)] {
+            let ray = camera.chief_ray(st).unwrap();
+            let point = ray.evaluate(5.0 / ray.direction.length());
+            let (s, t) = camera.project(point).unwrap();
+            assert!((s - st.0).abs() < 2e-3 && (t - st.1).abs() < 2e-3);
+        }
+        assert_eq!(camera.project(Vec3::new(0.0, 0.0, 1.0)), None);
+    }
+}
@@ -0,0 +1,204 @@
+use super::{
+    ods::{Eye, Ods},
+    perspective::Perspective,
+    projection::{Camera, Projection},
+};
+use crate::{ray::Ray, vec3::Vec3};
+
+/// How the views of the two eyes share the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// Left eye in the left half, right eye in the right half.
+    SideBySide,
+    /// Left eye in the top half, right eye in the bottom half.
+    OverUnder,
+}
+
+impl StereoLayout {
+    /// Resolution of an image holding two views of the given resolution.
+    pub fn image_resolution(self, view: (u32, u32)) -> (u32, u32) {
+        match self {
+            StereoLayout::SideBySide => (2 * view.0, view.1),
+            StereoLayout::OverUnder => (view.0, 2 * view.1),
+        }
+    }
+}
+
+/// How the eyes of a perspective rig are aimed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoMode {
+    /// Parallel views converging at infinity.
+    Parallel,
+    /// Parallel eyes looking through a shared off-center window, so that
+    /// objects at `convergence` distance appear at the screen.
+    OffAxis { convergence: f64 },
+}
+
+/// Pair of cameras rendered into one image, the views of the eyes are placed
+/// according to `layout`. The image should be twice as wide or high as the
+/// view of each eye.
+#[derive(Clone, Debug)]
+pub struct Stereo {
+    pub left: Box<Camera>,
+    pub right: Box<Camera>,
+    pub layout: StereoLayout,
+}
+
+impl Stereo {
+    pub fn new(left: Camera, right: Camera, layout: StereoLayout) -> Stereo {
+        Stereo {
+            left: Box::new(left),
+            right: Box::new(right),
+            layout,
+        }
+    }
+
+    /// Rig with the eyes `interocular` apart on either side of `camera`. With
+    /// the off-axis mode the image plane, and with it the plane in focus, is
+    /// moved to the convergence distance.
+    pub fn perspective(
+        camera: Perspective,
+        interocular: f64,
+        mode: StereoMode,
+        layout: StereoLayout,
+    ) -> Stereo {
+        let right = camera.horizontal.unitize();
+        let eye = |side: f64| {
+            let mut eye = match mode {
+                StereoMode::Parallel => camera.clone(),
+                StereoMode::OffAxis { convergence } => {
+                    camera.clone().with_focus_distance(convergence)
+                }
+            };
+            let offset = side * interocular / 2.0 * right;
+            eye.origin += offset;
+            if mode == StereoMode::Parallel {
+                eye.lower_left_corner += offset;
+            }
+            eye
+        };
+        Stereo::new(eye(-1.0).into(), eye(1.0).into(), layout)
+    }
+
+    /// Omni-directional stereo panorama, each eye covering every direction
+    /// around `look_from`.
+    pub fn ods(
+        look_from: Vec3,
+        look_at: Vec3,
+        up: Vec3,
+        interocular: f64,
+        layout: StereoLayout,
+    ) -> Stereo {
+        let eye = |eye| Ods::new(look_from, look_at, up, interocular, eye).into();
+        Stereo::new(eye(Eye::Left), eye(Eye::Right), layout)
+    }
+
+    /// Camera of the eye seeing the image point `st`, and the point in its view.
+    fn eye(&self, st: (f64, f64)) -> (&Camera, (f64, f64)) {
+        let (s, t) = st;
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => (&self.left, (2.0 * s, t)),
+            StereoLayout::SideBySide => (&self.right, (2.0 * s - 1.0, t)),
+            StereoLayout::OverUnder if t >= 0.5 => (&self.left, (s, 2.0 * t - 1.0)),
+            StereoLayout::OverUnder => (&self.right, (s, 2.0 * t)),
+        }
+    }
+}
+
+impl Projection for Stereo {
+    fn get_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (camera, st) = self.eye(st);
+        camera.get_ray(st)
+    }
+
+    fn chief_ray(&self, st: (f64, f64)) -> Option<Ray> {
+        let (camera, st) = self.eye(st);
+        camera.chief_ray(st)
+    }
+
+    fn get_weighted_ray(&self, st: (f64, f64)) -> Option<(Ray, f64)> {
+        let (camera, st) = self.eye(st);
+        camera.get_weighted_ray(st)
+    }
+
+    /// Position of `point` in the view of the left eye.
+    fn project(&self, point: Vec3) -> Option<(f64, f64)> {
+        let (s, t) = self.left.project(point)?;
+        Some(match self.layout {
+            StereoLayout::SideBySide => (s / 2.0, t),
+            StereoLayout::OverUnder => (s, (t + 1.0) / 2.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn camera() -> Perspective {
+        Perspective::new(
+            Vec3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+        )
+    }
+
+    #[test]
+    fn off_axis_eyes_converge() {
+        let rig = Stereo::perspective(
+            camera(),
+            0.064,
+            StereoMode::OffAxis { convergence: 3.0 },
+            StereoLayout::SideBySide,
+        );
+        let left = rig.get_ray((0.25, 0.5)).unwrap();
+        let right = rig.get_ray((0.75, 0.5)).unwrap();
+        assert_approx_eq!(left.origin.x, -0.032);
+        assert_approx_eq!(right.origin.x, 0.032);
+        let at = |ray: Ray| ray.origin + ray.direction * (3.0 / -ray.direction.z);
+        assert_approx_eq!((at(left) - Vec3::new(0.0, 0.0, -3.0)).length(), 0.0);
+        assert_approx_eq!((at(right) - Vec3::new(0.0, 0.0, -3.0)).length(), 0.0);
+    }
+
+    #[test]
+    fn project_into_left_view() {
+        let point = Vec3::new(0.2, -0.1, -2.0);
+        for layout in [StereoLayout::SideBySide, StereoLayout::OverUnder] {
+            let rig = Stereo::perspective(camera(), 0.064, StereoMode::Parallel, layout);
+            let st = rig.project(point).unwrap();
+            let ray = rig.get_ray(st).unwrap();
+            assert_approx_eq!(ray.origin.x, -0.032);
+            let to_point = (point - ray.origin).unitize();
+            assert_approx_eq!((ray.direction - to_point).length(), 0.0);
+        }
+    }
+
+    #[test]
+    fn parallel_eyes_look_alike() {
+        let rig = Stereo::perspective(
+            camera(),
+            0.064,
+            StereoMode::Parallel,
+            StereoLayout::OverUnder,
+        );
+        let left = rig.get_ray((0.3, 0.9)).unwrap();
+        let right = rig.get_ray((0.3, 0.4)).unwrap();
+        assert_approx_eq!((left.direction - right.direction).length(), 0.0);
+        assert_approx_eq!(
+            (right.origin - left.origin - Vec3::new(0.064, 0.0, 0.0)).length(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn image_holds_both_views() {
+        let view = (400, 200);
+        assert_eq!(StereoLayout::SideBySide.image_resolution(view), (800, 200));
+        assert_eq!(StereoLayout::OverUnder.image_resolution(view), (400, 400));
+    }
+}
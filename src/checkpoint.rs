@@ -0,0 +1,212 @@
+use crate::{
+    camera::projection::Camera,
+    render::{render_pass, Film},
+    shape::hittable::Hittable,
+};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 1;
+/// Magic, version, width, height, seed and next sample index.
+const HEADER_LEN: u64 = 4 + 4 + 4 + 4 + 8 + 4;
+/// Three `f64` channels and a `u32` sample count per pixel.
+const PIXEL_LEN: u64 = 3 * 8 + 4;
+
+/// State of a progressive render that can be written to disk and resumed.
+///
+/// Together with the seed and the index of the next sample pass the accumulated
+/// film is enough to continue exactly where an interrupted render stopped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    pub seed: u64,
+    pub next_sample: u32,
+    pub film: Film,
+}
+
+impl Checkpoint {
+    pub fn new(resolution: (u32, u32), seed: u64) -> Checkpoint {
+        Checkpoint {
+            seed,
+            next_sample: 0,
+            film: Film::new(resolution),
+        }
+    }
+
+    /// Renders sample passes until every pixel has `samples_per_pixel` samples,
+    /// saving to `path` whenever `interval` has passed and once at the end.
+    pub fn render<T: Hittable + Sync>(
+        &mut self,
+        scene: &T,
+        camera: &Camera,
+        samples_per_pixel: u32,
+        path: &Path,
+        interval: Duration,
+    ) -> io::Result<()> {
+        let mut last_save = Instant::now();
+        while self.next_sample < samples_per_pixel {
+            render_pass(&mut self.film, scene, camera, self.seed, self.next_sample);
+            self.next_sample += 1;
+            if last_save.elapsed() >= interval {
+                self.save(path)?;
+                last_save = Instant::now();
+            }
+        }
+        self.save(path)
+    }
+
+    /// Writes the checkpoint to a temporary file first and renames it, so a crash
+    /// while saving never destroys the previous checkpoint.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let tmp = path.with_file_name(file_name);
+        {
+            let mut w = BufWriter::new(File::create(&tmp)?);
+            w.write_all(MAGIC)?;
+            w.write_all(&VERSION.to_le_bytes())?;
+            w.write_all(&self.film.width.to_le_bytes())?;
+            w.write_all(&self.film.height.to_le_bytes())?;
+            w.write_all(&self.seed.to_le_bytes())?;
+            w.write_all(&self.next_sample.to_le_bytes())?;
+            self.film.write_to(&mut w)?;
+            w.flush()?;
+        }
+        fs::rename(tmp, path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Checkpoint> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a checkpoint file"));
+        }
+        if read_u32(&mut r)? != VERSION {
+            return Err(invalid_data("unsupported checkpoint version"));
+        }
+        let width = read_u32(&mut r)?;
+        let height = read_u32(&mut r)?;
+        let seed = read_u64(&mut r)?;
+        let next_sample = read_u32(&mut r)?;
+
+        // Check the size before allocating so a corrupt header can't request a
+        // huge film.
+        let expected_len = (width as u64)
+            .checked_mul(height as u64)
+            .and_then(|pixels| pixels.checked_mul(PIXEL_LEN))
+            .and_then(|len| len.checked_add(HEADER_LEN));
+        if expected_len != Some(file_len) {
+            return Err(invalid_data(
+                "checkpoint size does not match its resolution",
+            ));
+        }
+
+        let film = Film::read_from(&mut r, (width, height))?;
+
+        Ok(Checkpoint {
+            seed,
+            next_sample,
+            film,
+        })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_scene::create_5_sphere_scene;
+    use crate::vec3::Vec3;
+    use std::env::temp_dir;
+
+    #[test]
+    fn save_load_roundtrip() {
+        let mut checkpoint = Checkpoint::new((3, 2), 7);
+        checkpoint.next_sample = 4;
+        checkpoint.film.pixels[1] = Vec3::new(0.5, 1.5, 2.5);
+        checkpoint.film.samples[1] = 4;
+
+        let path = temp_dir().join("raytrace_checkpoint_roundtrip.rtck");
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint, loaded);
+    }
+
+    #[test]
+    fn save_tmp_extension() {
+        let checkpoint = Checkpoint::new((2, 2), 1);
+        let path = temp_dir().join("raytrace_checkpoint_ext.tmp");
+        checkpoint.save(&path).unwrap();
+        assert!(path.exists());
+        assert!(!temp_dir().join("raytrace_checkpoint_ext.tmp.tmp").exists());
+        let loaded = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint, loaded);
+    }
+
+    #[test]
+    fn load_rejects_oversized_header() {
+        let path = temp_dir().join("raytrace_checkpoint_oversized.rtck");
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        let error = Checkpoint::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn resumed_render_matches_uninterrupted() {
+        let resolution = (8, 4);
+        let (camera, world) = create_5_sphere_scene(8.0, 4.0);
+        let scene = world.as_slice();
+
+        let full_path = temp_dir().join("raytrace_checkpoint_full.rtck");
+        let mut full = Checkpoint::new(resolution, 3);
+        full.render(&scene, &camera, 4, &full_path, Duration::MAX)
+            .unwrap();
+
+        let resumed_path = temp_dir().join("raytrace_checkpoint_resumed.rtck");
+        let mut first = Checkpoint::new(resolution, 3);
+        first
+            .render(&scene, &camera, 2, &resumed_path, Duration::MAX)
+            .unwrap();
+        let mut resumed = Checkpoint::load(&resumed_path).unwrap();
+        resumed
+            .render(&scene, &camera, 4, &resumed_path, Duration::MAX)
+            .unwrap();
+
+        fs::remove_file(&full_path).unwrap();
+        fs::remove_file(&resumed_path).unwrap();
+        assert_eq!(full, resumed);
+    }
+}
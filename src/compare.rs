@@ -0,0 +1,411 @@
+use crate::{
+    linear_image::LinearImage,
+    render::linear_srgb_transfer_function,
+    utility::{lerp, luminance},
+    vec3::Vec3,
+};
+
+/// Error metrics of a test image measured against a reference image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metrics {
+    pub mse: f64,
+    pub rmse: f64,
+    /// Mean squared error relative to the squared reference value.
+    pub rel_mse: f64,
+    /// Peak signal to noise ratio in decibels, for a peak value of 1.
+    pub psnr: f64,
+    /// Mean structural similarity of the sRGB encoded luminance.
+    pub ssim: f64,
+    /// Mean perceptual error, see [`flip`].
+    pub flip: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub metrics: Metrics,
+    /// Per pixel FLIP error in `[0, 1]`.
+    pub error_map: Vec<f64>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Comparison {
+    /// False colour visualisation of the per pixel error as 8 bit RGB.
+    pub fn error_image(&self) -> Vec<u8> {
+        self.error_map
+            .iter()
+            .flat_map(|&e| {
+                let c = false_colour(e);
+                [
+                    (c.x * 255.99) as u8,
+                    (c.y * 255.99) as u8,
+                    (c.z * 255.99) as u8,
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Pixels per degree of visual angle used for the perceptual metric, matching a
+/// 0.7 m wide 4K monitor seen from 0.7 m.
+pub const DEFAULT_PIXELS_PER_DEGREE: f64 = 67.0;
+
+pub fn compare(reference: &LinearImage, test: &LinearImage, pixels_per_degree: f64) -> Comparison {
+    assert_eq!(
+        (reference.width, reference.height),
+        (test.width, test.height),
+        "image sizes differ"
+    );
+    let n = 3.0 * reference.pixels.len() as f64;
+    let (mut se, mut rel_se) = (0.0, 0.0);
+    for (r, t) in reference.pixels.iter().zip(test.pixels.iter()) {
+        for axis in 0..3 {
+            let d = (t[axis] - r[axis]).powi(2);
+            se += d;
+            rel_se += d / (r[axis].powi(2) + 0.01);
+        }
+    }
+    let mse = se / n;
+    let error_map = flip(reference, test, pixels_per_degree);
+
+    Comparison {
+        metrics: Metrics {
+            mse,
+            rmse: mse.sqrt(),
+            rel_mse: rel_se / n,
+            psnr: 10.0 * (1.0 / mse).log10(),
+            ssim: ssim(reference, test),
+            flip: error_map.iter().sum::<f64>() / error_map.len() as f64,
+        },
+        error_map,
+        width: reference.width,
+        height: reference.height,
+    }
+}
+
+/// Mean SSIM over 11 by 11 Gaussian windows of the sRGB encoded luminance.
+pub fn ssim(reference: &LinearImage, test: &LinearImage) -> f64 {
+    let (w, h) = (reference.width as usize, reference.height as usize);
+    let gray = |img: &LinearImage| -> Vec<f64> {
+        img.pixels
+            .iter()
+            .map(|p| linear_srgb_transfer_function(luminance(*p).clamp(0.0, 1.0)))
+            .collect()
+    };
+    let (x, y) = (gray(reference), gray(test));
+    let product =
+        |a: &[f64], b: &[f64]| -> Vec<f64> { a.iter().zip(b).map(|(a, b)| a * b).collect() };
+
+    let kernel = gaussian_kernel(1.5, 5);
+    let blur = |img: &[f64]| convolve_separable(img, w, h, &kernel, &kernel);
+    let (mx, my) = (blur(&x), blur(&y));
+    let (sxx, syy, sxy) = (
+        blur(&product(&x, &x)),
+        blur(&product(&y, &y)),
+        blur(&product(&x, &y)),
+    );
+
+    let (c1, c2) = (0.01f64.powi(2), 0.03f64.powi(2));
+    let total: f64 = (0..w * h)
+        .map(|i| {
+            let (vx, vy, cxy) = (
+                sxx[i] - mx[i] * mx[i],
+                syy[i] - my[i] * my[i],
+                sxy[i] - mx[i] * my[i],
+            );
+            ((2.0 * mx[i] * my[i] + c1) * (2.0 * cxy + c2))
+                / ((mx[i] * mx[i] + my[i] * my[i] + c1) * (vx + vy + c2))
+        })
+        .sum();
+    total / (w * h) as f64
+}
+
+/// Per pixel LDR-FLIP error (Andersson et al. 2020) of the images after clamping
+/// to `[0, 1]`.
+///
+/// The colour difference is measured in a Hunt adjusted CIELAB space after
+/// filtering with contrast sensitivity functions for the given viewing
+/// distance, and is amplified where edges and points differ.
+pub fn flip(reference: &LinearImage, test: &LinearImage, pixels_per_degree: f64) -> Vec<f64> {
+    let (w, h) = (reference.width as usize, reference.height as usize);
+    let clamp = |p: Vec3| {
+        Vec3::new(
+            p.x.clamp(0.0, 1.0),
+            p.y.clamp(0.0, 1.0),
+            p.z.clamp(0.0, 1.0),
+        )
+    };
+    let (reference, test) = (reference.map(clamp), test.map(clamp));
+
+    // Colour pipeline.
+    let filters = [
+        csf_kernel(1.0, 0.0047, 0.0, 1e-5, pixels_per_degree),
+        csf_kernel(1.0, 0.0053, 0.0, 1e-5, pixels_per_degree),
+        csf_kernel(34.1, 0.04, 13.5, 0.025, pixels_per_degree),
+    ];
+    let filtered_lab = |img: &LinearImage| -> Vec<Vec3> {
+        let ycxcz: Vec<Vec3> = img
+            .pixels
+            .iter()
+            .map(|p| xyz_to_ycxcz(linear_rgb_to_xyz(*p)))
+            .collect();
+        let mut channels = [vec![], vec![], vec![]];
+        for (axis, channel) in channels.iter_mut().enumerate() {
+            let values: Vec<f64> = ycxcz.iter().map(|p| p[axis]).collect();
+            *channel = convolve_separable(&values, w, h, &filters[axis], &filters[axis]);
+        }
+        (0..w * h)
+            .map(|i| {
+                let rgb = xyz_to_linear_rgb(ycxcz_to_xyz(Vec3::new(
+                    channels[0][i],
+                    channels[1][i],
+                    channels[2][i],
+                )));
+                hunt(xyz_to_lab(linear_rgb_to_xyz(clamp(rgb))))
+            })
+            .collect()
+    };
+    let (lab_ref, lab_test) = (filtered_lab(&reference), filtered_lab(&test));
+
+    let (qc, pc, pt) = (0.7, 0.4, 0.95);
+    let green = hunt(xyz_to_lab(linear_rgb_to_xyz(Vec3::new(0.0, 1.0, 0.0))));
+    let blue = hunt(xyz_to_lab(linear_rgb_to_xyz(Vec3::new(0.0, 0.0, 1.0))));
+    let cmax = hyab(green, blue).powf(qc);
+    let colour_error = |a: Vec3, b: Vec3| {
+        let e = hyab(a, b).powf(qc);
+        match e < pc * cmax {
+            true => e * pt / (pc * cmax),
+            false => pt + (e - pc * cmax) / (cmax - pc * cmax) * (1.0 - pt),
+        }
+    };
+
+    // Feature pipeline on the normalised achromatic channel.
+    let sigma = 0.082 * pixels_per_degree;
+    let radius = (3.0 * sigma).ceil() as usize;
+    let smooth = gaussian_kernel(sigma, radius);
+    let edge = normalize_signed(derivative_kernel(sigma, radius, 1));
+    let point = normalize_signed(derivative_kernel(sigma, radius, 2));
+    let features = |img: &LinearImage| -> (Vec<f64>, Vec<f64>) {
+        let y: Vec<f64> = img
+            .pixels
+            .iter()
+            .map(|p| (xyz_to_lab(linear_rgb_to_xyz(*p)).x + 16.0) / 116.0)
+            .collect();
+        let magnitude = |kernel: &[f64]| -> Vec<f64> {
+            let dx = convolve_separable(&y, w, h, kernel, &smooth);
+            let dy = convolve_separable(&y, w, h, &smooth, kernel);
+            dx.iter().zip(dy).map(|(x, y)| x.hypot(y)).collect()
+        };
+        (magnitude(&edge), magnitude(&point))
+    };
+    let (edges_ref, points_ref) = features(&reference);
+    let (edges_test, points_test) = features(&test);
+
+    let qf = 0.5;
+    (0..w * h)
+        .map(|i| {
+            let feature_error = (f64::max(
+                (edges_ref[i] - edges_test[i]).abs(),
+                (points_ref[i] - points_test[i]).abs(),
+            ) / 2f64.sqrt())
+            .powf(qf);
+            colour_error(lab_ref[i], lab_test[i]).powf(1.0 - feature_error)
+        })
+        .collect()
+}
+
+const WHITE: Vec3 = Vec3 {
+    x: 0.950428545,
+    y: 1.0,
+    z: 1.088900371,
+};
+
+fn linear_rgb_to_xyz(c: Vec3) -> Vec3 {
+    Vec3::new(
+        0.4124564 * c.x + 0.3575761 * c.y + 0.1804375 * c.z,
+        0.2126729 * c.x + 0.7151522 * c.y + 0.0721750 * c.z,
+        0.0193339 * c.x + 0.1191920 * c.y + 0.9503041 * c.z,
+    )
+}
+
+fn xyz_to_linear_rgb(c: Vec3) -> Vec3 {
+    Vec3::new(
+        3.2404542 * c.x - 1.5371385 * c.y - 0.4985314 * c.z,
+        -0.9692660 * c.x + 1.8760108 * c.y + 0.0415560 * c.z,
+        0.0556434 * c.x - 0.2040259 * c.y + 1.0572252 * c.z,
+    )
+}
+
+fn xyz_to_ycxcz(c: Vec3) -> Vec3 {
+    let c = c / WHITE;
+    Vec3::new(116.0 * c.y - 16.0, 500.0 * (c.x - c.y), 200.0 * (c.y - c.z))
+}
+
+fn ycxcz_to_xyz(c: Vec3) -> Vec3 {
+    let y = (c.x + 16.0) / 116.0;
+    Vec3::new(y + c.y / 500.0, y, y - c.z / 200.0) * WHITE
+}
+
+fn xyz_to_lab(c: Vec3) -> Vec3 {
+    let f = |t: f64| {
+        let delta: f64 = 6.0 / 29.0;
+        match t > delta.powi(3) {
+            true => t.cbrt(),
+            false => t / (3.0 * delta * delta) + 4.0 / 29.0,
+        }
+    };
+    let c = c / WHITE;
+    let (fx, fy, fz) = (f(c.x), f(c.y), f(c.z));
+    Vec3::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+fn hunt(lab: Vec3) -> Vec3 {
+    Vec3::new(lab.x, 0.01 * lab.x * lab.y, 0.01 * lab.x * lab.z)
+}
+
+fn hyab(a: Vec3, b: Vec3) -> f64 {
+    (a.x - b.x).abs() + (a.y - b.y).hypot(a.z - b.z)
+}
+
+/// Maps an error in `[0, 1]` to a colour going from black through purple and
+/// orange to pale yellow.
+fn false_colour(e: f64) -> Vec3 {
+    let stops = [
+        Vec3::new(0.0, 0.0, 0.016),
+        Vec3::new(0.232, 0.059, 0.437),
+        Vec3::new(0.550, 0.161, 0.506),
+        Vec3::new(0.868, 0.288, 0.409),
+        Vec3::new(0.994, 0.624, 0.427),
+        Vec3::new(0.987, 0.991, 0.750),
+    ];
+    let x = e.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let i = (x as usize).min(stops.len() - 2);
+    lerp(stops[i], stops[i + 1], x - i as f64)
+}
+
+fn gaussian_kernel(sigma: f64, radius: usize) -> Vec<f64> {
+    let kernel: Vec<f64> = (0..=2 * radius)
+        .map(|i| {
+            let x = i as f64 - radius as f64;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    kernel.iter().map(|k| k / sum).collect()
+}
+
+/// First or second derivative of a Gaussian.
+fn derivative_kernel(sigma: f64, radius: usize, order: u32) -> Vec<f64> {
+    (0..=2 * radius)
+        .map(|i| {
+            let x = i as f64 - radius as f64;
+            let g = (-x * x / (2.0 * sigma * sigma)).exp();
+            match order {
+                1 => -x * g,
+                _ => (x * x / (sigma * sigma) - 1.0) * g,
+            }
+        })
+        .collect()
+}
+
+/// Scales positive weights to sum to one and negative weights to sum to minus one.
+fn normalize_signed(kernel: Vec<f64>) -> Vec<f64> {
+    let positive: f64 = kernel.iter().filter(|k| **k > 0.0).sum();
+    let negative: f64 = -kernel.iter().filter(|k| **k < 0.0).sum::<f64>();
+    kernel
+        .into_iter()
+        .map(|k| if k > 0.0 { k / positive } else { k / negative })
+        .collect()
+}
+
+/// Contrast sensitivity filter made of two Gaussians, with parameters given in
+/// degrees of visual angle.
+fn csf_kernel(a1: f64, b1: f64, a2: f64, b2: f64, pixels_per_degree: f64) -> Vec<f64> {
+    let pi2 = std::f64::consts::PI.powi(2);
+    let radius = (3.0 * (b1.max(b2) / (2.0 * pi2)).sqrt() * pixels_per_degree).ceil() as usize;
+    let kernel: Vec<f64> = (0..=2 * radius)
+        .map(|i| {
+            let x = (i as f64 - radius as f64) / pixels_per_degree;
+            let g =
+                |a: f64, b: f64| a * (std::f64::consts::PI / b).sqrt() * (-pi2 * x * x / b).exp();
+            g(a1, b1) + g(a2, b2)
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    kernel.iter().map(|k| k / sum).collect()
+}
+
+/// Convolves with `kx` along rows and `ky` along columns, clamping at the border.
+fn convolve_separable(img: &[f64], w: usize, h: usize, kx: &[f64], ky: &[f64]) -> Vec<f64> {
+    let pass = |src: &[f64], kernel: &[f64], horizontal: bool| -> Vec<f64> {
+        let r = (kernel.len() / 2) as isize;
+        (0..w * h)
+            .map(|i| {
+                let (x, y) = ((i % w) as isize, (i / w) as isize);
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| {
+                        let o = k as isize - r;
+                        let (sx, sy) = match horizontal {
+                            true => ((x + o).clamp(0, w as isize - 1), y),
+                            false => (x, (y + o).clamp(0, h as isize - 1)),
+                        };
+                        weight * src[sy as usize * w + sx as usize]
+                    })
+                    .sum()
+            })
+            .collect()
+    };
+    pass(&pass(img, kx, true), ky, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn image(f: impl Fn(u32, u32) -> Vec3) -> LinearImage {
+        let (width, height) = (16, 12);
+        LinearImage {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|i| f(i % width, i / width))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn identical_images() {
+        let a = image(|x, y| Vec3::new(x as f64 / 16.0, y as f64 / 12.0, 0.5));
+        let metrics = compare(&a, &a, DEFAULT_PIXELS_PER_DEGREE).metrics;
+        assert_eq!(metrics.mse, 0.0);
+        assert_eq!(metrics.psnr, f64::INFINITY);
+        assert_approx_eq!(metrics.ssim, 1.0);
+        assert_approx_eq!(metrics.flip, 0.0);
+    }
+
+    #[test]
+    fn metrics_grow_with_error() {
+        let a = image(|x, _| Vec3::new(0.5, x as f64 / 16.0, 0.2));
+        let slightly = a.map(|p| p * 1.05);
+        let very = a.map(|p| Vec3::new(1.0, 1.0, 1.0) - p);
+        let small = compare(&a, &slightly, DEFAULT_PIXELS_PER_DEGREE).metrics;
+        let large = compare(&a, &very, DEFAULT_PIXELS_PER_DEGREE).metrics;
+        assert!(0.0 < small.mse && small.mse < large.mse);
+        assert!(small.psnr > large.psnr);
+        assert!(small.ssim > large.ssim);
+        assert!(0.0 < small.flip && small.flip < large.flip && large.flip <= 1.0);
+    }
+
+    #[test]
+    fn black_and_white_flip() {
+        let black = image(|_, _| Vec3::origin());
+        let white = image(|_, _| Vec3::new(1.0, 1.0, 1.0));
+        let flip = compare(&black, &white, DEFAULT_PIXELS_PER_DEGREE)
+            .metrics
+            .flip;
+        assert!(flip > 0.9);
+    }
+}
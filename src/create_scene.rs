@@ -1,70 +1,174 @@
+use crate::animation::{Animated, Animation, CameraAnimation, Interpolation, Keyframe, Track};
+use crate::bvh::bvh::split_method::Middle;
+use crate::bvh::bvh::Bvh;
 use crate::material::dielectric::Dielectric;
 use crate::material::lambertian::Lambertian;
 use crate::material::metal::Metal;
+use crate::material::principled::Principled;
 use crate::material::scatterable::Material;
 use crate::shape::hittable::Shape;
+use crate::shape::moving_sphere::MovingSphere;
 use crate::shape::sphere::Sphere;
+use crate::transform::Transform;
 use crate::vec3::Vec3;
-use crate::{camera::Camera, shape::triangle_mesh::TriangleMesh};
-use obj::Obj;
+use crate::{
+    camera::{perspective::Perspective, projection::Camera},
+    shape::triangle_mesh::TriangleMesh,
+};
+use obj::{Obj, ObjMaterial};
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Names one of the built in scenes, so a scene can be referred to by name, for
+/// example when sending work to other processes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneDescription {
+    FiveSpheres,
+    Suzanne,
+    Book1Final,
+    MotionBlur,
+}
+
+impl SceneDescription {
+    /// Builds the scene for the given resolution and passes its bvh and camera to `f`.
+    pub fn with_scene<R>(self, resolution: (u32, u32), f: impl FnOnce(&Bvh, &Camera) -> R) -> R {
+        let (camera, mesh, mut world) = self.build(resolution);
+        world.extend(
+            mesh.iter()
+                .flat_map(TriangleMesh::triangles)
+                .map(Shape::Triangle),
+        );
+        f(&Bvh::build(&mut world, Middle), &camera)
+    }
+
+    /// Hash of the camera and objects of the scene at the given resolution.
+    ///
+    /// A scene is only sent by name, but what gets built depends on the mesh
+    /// files on disk and on the build, so two processes compare hashes to make
+    /// sure they render the same scene.
+    pub fn content_hash(self, resolution: (u32, u32)) -> u64 {
+        let (camera, mesh, world) = self.build(resolution);
+        let mut hasher = Fnv1a(0xcbf29ce484222325);
+        write!(hasher, "{:?} {:?} {:?}", camera, mesh, world).unwrap();
+        hasher.0
+    }
+
+    fn build<'a>(
+        self,
+        resolution: (u32, u32),
+    ) -> (Camera, Option<TriangleMesh>, Vec<Shape<'a, 'a>>) {
+        let (width, height) = (resolution.0 as f64, resolution.1 as f64);
+        match self {
+            SceneDescription::FiveSpheres => {
+                let (camera, world) = create_5_sphere_scene(width, height);
+                (camera, None, world)
+            }
+            SceneDescription::Suzanne => {
+                let (camera, mesh, world) = create_suzanne_scene(width, height);
+                (camera, Some(mesh), world)
+            }
+            SceneDescription::Book1Final => {
+                let (camera, world) = create_book_1_final_scene(width, height);
+                (camera, None, world)
+            }
+            SceneDescription::MotionBlur => {
+                let (camera, world) = create_motion_blur_scene(width, height);
+                (camera, None, world)
+            }
+        }
+    }
+}
+
+/// 64 bit FNV-1a hash of formatted text.
+struct Fnv1a(u64);
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SceneDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SceneDescription::FiveSpheres => "five-spheres",
+            SceneDescription::Suzanne => "suzanne",
+            SceneDescription::Book1Final => "book-1-final",
+            SceneDescription::MotionBlur => "motion-blur",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SceneDescription {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "five-spheres" => Ok(SceneDescription::FiveSpheres),
+            "suzanne" => Ok(SceneDescription::Suzanne),
+            "book-1-final" => Ok(SceneDescription::Book1Final),
+            "motion-blur" => Ok(SceneDescription::MotionBlur),
+            _ => Err(format!("unknown scene: {}", s)),
+        }
+    }
+}
 
 #[allow(dead_code)]
 pub fn create_5_sphere_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Shape<'a, 'a>>) {
     let look_from = Vec3::new(3.0, 3.0, 2.0);
     let look_at = Vec3::new(0.0, 0.0, -1.0);
     let up = Vec3::new(0.0, 1.0, 0.0);
-    let focus_distance = (look_from - look_at).length();
     let aperture = 0.0;
-    let camera = Camera::new(
+    let camera = Perspective::new(
         look_from,
         look_at,
         up,
         20.0,
         width / height,
         aperture,
-        focus_distance,
+        (look_from - look_at).length(),
     );
     let scene: Vec<Shape> = vec![
         Shape::Sph(Sphere::new(
             Vec3::new(0.0, 0.0, -1.0),
             0.5,
             Material::Lambertian(Lambertian {
-                albedo: Vec3::new(0.1, 0.2, 0.5),
+                albedo: Vec3::new(0.1, 0.2, 0.5).into(),
             }),
         )),
         Shape::Sph(Sphere::new(
             Vec3::new(0.0, -100.5, -1.0),
             100.0,
             Material::Lambertian(Lambertian {
-                albedo: Vec3::new(0.8, 0.8, 0.0),
+                albedo: Vec3::new(0.8, 0.8, 0.0).into(),
             }),
         )),
         Shape::Sph(Sphere::new(
             Vec3::new(1.0, 0.0, -1.0),
             0.5,
-            Material::Metal(Metal {
-                albedo: Vec3::new(0.8, 0.6, 0.2),
-                roughness: 0.2,
-            }),
+            Material::Metal(Metal::new(Vec3::new(0.8, 0.6, 0.2).into(), 0.2)),
         )),
         Shape::Sph(Sphere::new(
             Vec3::new(-1.0, 0.0, -1.0),
             0.5,
-            Material::Dielectric(Dielectric {
-                index_of_refraction: 1.5,
-            }),
+            Material::Dielectric(Dielectric::new(1.5)),
         )),
+        // Air bubble displacing the glass around it.
         Shape::Sph(Sphere::new(
             Vec3::new(-1.0, 0.0, -1.0),
-            -0.45,
-            Material::Dielectric(Dielectric {
-                index_of_refraction: 1.5,
-            }),
+            0.45,
+            Material::Dielectric(Dielectric::new(1.0).with_priority(1)),
         )),
     ];
 
+    let camera = camera.autofocus(&scene.as_slice(), (0.5, 0.5)).into();
     (camera, scene)
 }
 
@@ -75,16 +179,15 @@ pub fn create_suzanne_scene<'a>(
     let look_from = Vec3::new(5.0, 0.5, 9.0);
     let look_at = Vec3::new(0.0, 0.0, 0.0);
     let up = Vec3::new(0.0, 1.0, 0.0);
-    let focus_distance = (look_from - look_at).length();
     let aperture = 0.0;
-    let camera = Camera::new(
+    let camera = Perspective::new(
         look_from,
         look_at,
         up,
         20.0,
         width / height,
         aperture,
-        focus_distance,
+        (look_from - look_at).length(),
     );
     let mesh = get_suzanne();
 
@@ -96,7 +199,7 @@ pub fn create_suzanne_scene<'a>(
         Vec3::new(0.0, -1001.0, 0.0),
         1000.0,
         Material::Lambertian(Lambertian {
-            albedo: Vec3::new(0.5, 0.5, 0.5),
+            albedo: Vec3::new(0.5, 0.5, 0.5).into(),
         }),
     )));
     for a in -11..11 {
@@ -120,40 +223,114 @@ pub fn create_suzanne_scene<'a>(
                                 rng.gen::<f64>() * rng.gen::<f64>(),
                                 rng.gen::<f64>() * rng.gen::<f64>(),
                                 rng.gen::<f64>() * rng.gen::<f64>(),
-                            ),
+                            )
+                            .into(),
                         }),
                     )));
                 } else if choose_material < 0.85 {
                     objects.push(Shape::Sph(Sphere::new(
                         center,
                         0.2,
-                        Material::Metal(Metal {
-                            albedo: Vec3::new(
+                        Material::Metal(Metal::new(
+                            Vec3::new(
                                 0.5 * (1.0 + rng.gen::<f64>()),
                                 0.5 * (1.0 + rng.gen::<f64>()),
                                 0.5 * (1.0 + rng.gen::<f64>()),
-                            ),
-                            roughness: 0.5 * rng.gen::<f64>(),
-                        }),
+                            )
+                            .into(),
+                            0.5 * rng.gen::<f64>(),
+                        )),
                     )));
                 } else {
                     objects.push(Shape::Sph(Sphere::new(
                         center,
                         0.2,
-                        Material::Dielectric(Dielectric {
-                            index_of_refraction: 1.5,
-                        }),
+                        Material::Dielectric(Dielectric::new(1.5)),
                     )));
                 }
             }
         }
     }
 
+    // Suzanne fills the center of the image.
+    let triangles: Vec<Shape> = mesh.triangles().map(Shape::Triangle).collect();
+    let camera = camera.autofocus(&triangles.as_slice(), (0.5, 0.5)).into();
+
     (camera, mesh, objects)
 }
 
+/// Suzanne scene with the mesh making one full turn about the vertical axis
+/// every `seconds` seconds.
+pub fn create_suzanne_turntable(width: f64, height: f64, seconds: f64) -> Animation {
+    let (_, mesh, world) = create_suzanne_scene(width, height);
+    let spheres = world
+        .into_iter()
+        .filter_map(|shape| match shape {
+            Shape::Sph(sphere) => Some(Animated {
+                object: sphere,
+                transform: None,
+            }),
+            _ => None,
+        })
+        .collect();
+    let rotation = Track::new(
+        vec![
+            Keyframe {
+                time: 0.0,
+                value: Transform::identity(),
+            },
+            Keyframe {
+                time: seconds,
+                value: Transform::rotate(Vec3::new(0.0, 360.0, 0.0)),
+            },
+        ],
+        Interpolation::Linear,
+    );
+
+    Animation {
+        camera: CameraAnimation {
+            look_from: Track::constant(Vec3::new(5.0, 0.5, 9.0)),
+            look_at: Track::constant(Vec3::origin()),
+            vertical_fov: Track::constant(20.0),
+            up: Vec3::new(0.0, 1.0, 0.0),
+            aperture: 0.0,
+        },
+        spheres,
+        meshes: vec![Animated {
+            object: mesh,
+            transform: Some(rotation),
+        }],
+        frames_per_second: 24.0,
+    }
+}
+
 fn get_suzanne() -> TriangleMesh {
-    let obj = Obj::load("./models/suzanne.obj").unwrap();
+    load_mesh(Path::new("./models/suzanne.obj"))
+}
+
+/// Loads an OBJ file as one mesh. The first material from its MTL libraries
+/// is mapped with [`Principled::from_mtl`], meshes without one are gray.
+fn load_mesh(path: &Path) -> TriangleMesh {
+    let mut obj = Obj::load(path).unwrap();
+    obj.load_mtls().unwrap();
+    let polys = || {
+        obj.data
+            .objects
+            .iter()
+            .flat_map(|obj| obj.groups.iter().flat_map(|g| g.polys.iter()))
+    };
+    let mtl = obj
+        .data
+        .objects
+        .iter()
+        .flat_map(|obj| obj.groups.iter())
+        .find_map(|group| match &group.material {
+            Some(ObjMaterial::Mtl(mtl)) => Some(mtl.clone()),
+            _ => None,
+        });
+    let uv_indices: Option<Vec<[usize; 3]>> = polys()
+        .map(|poly| Some([poly.0[0].1?, poly.0[1].1?, poly.0[2].1?]))
+        .collect();
 
     TriangleMesh {
         vertices: obj
@@ -162,21 +339,22 @@ fn get_suzanne() -> TriangleMesh {
             .iter()
             .map(|v| Vec3::new(v[0] as f64, v[1] as f64, v[2] as f64))
             .collect(),
-        vertex_indices: obj
+        vertex_indices: polys()
+            .map(|poly| [poly.0[0].0, poly.0[1].0, poly.0[2].0])
+            .collect(),
+        uvs: obj
             .data
-            .objects
+            .texture
             .iter()
-            .flat_map(|obj| {
-                obj.groups.iter().flat_map(|g| {
-                    g.polys
-                        .iter()
-                        .map(|poly| [poly.0[0].0, poly.0[1].0, poly.0[2].0])
-                })
-            })
+            .map(|uv| (uv[0] as f64, uv[1] as f64))
             .collect(),
-        material: Material::Lambertian(Lambertian {
-            albedo: Vec3::new(0.6, 0.6, 0.6),
-        }),
+        uv_indices: uv_indices.unwrap_or_default(),
+        material: match mtl {
+            Some(mtl) => Material::Principled(Principled::from_mtl(&mtl)),
+            None => Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.6, 0.6, 0.6).into(),
+            }),
+        },
     }
 }
 
@@ -184,16 +362,15 @@ pub fn create_book_1_final_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Sh
     let look_from = Vec3::new(13.0, 2.0, 3.0);
     let look_at = Vec3::new(0.0, 0.0, 0.0);
     let up = Vec3::new(0.0, 1.0, 0.0);
-    let focus_distance = 10.0;
     let aperture = 0.1;
-    let camera = Camera::new(
+    let camera = Perspective::new(
         look_from,
         look_at,
         up,
         20.0,
         width / height,
         aperture,
-        focus_distance,
+        (look_from - look_at).length(),
     );
 
     let mut objects: Vec<Shape> = vec![];
@@ -204,7 +381,7 @@ pub fn create_book_1_final_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Sh
         Vec3::new(0.0, -1000.0, 0.0),
         1000.0,
         Material::Lambertian(Lambertian {
-            albedo: Vec3::new(0.5, 0.5, 0.5),
+            albedo: Vec3::new(0.5, 0.5, 0.5).into(),
         }),
     )));
     for a in -11..11 {
@@ -225,29 +402,29 @@ pub fn create_book_1_final_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Sh
                                 rng.gen::<f64>() * rng.gen::<f64>(),
                                 rng.gen::<f64>() * rng.gen::<f64>(),
                                 rng.gen::<f64>() * rng.gen::<f64>(),
-                            ),
+                            )
+                            .into(),
                         }),
                     )));
                 } else if choose_material < 0.95 {
                     objects.push(Shape::Sph(Sphere::new(
                         center,
                         0.2,
-                        Material::Metal(Metal {
-                            albedo: Vec3::new(
+                        Material::Metal(Metal::new(
+                            Vec3::new(
                                 0.5 * (1.0 + rng.gen::<f64>()),
                                 0.5 * (1.0 + rng.gen::<f64>()),
                                 0.5 * (1.0 + rng.gen::<f64>()),
-                            ),
-                            roughness: 0.5 * rng.gen::<f64>(),
-                        }),
+                            )
+                            .into(),
+                            0.5 * rng.gen::<f64>(),
+                        )),
                     )));
                 } else {
                     objects.push(Shape::Sph(Sphere::new(
                         center,
                         0.2,
-                        Material::Dielectric(Dielectric {
-                            index_of_refraction: 1.5,
-                        }),
+                        Material::Dielectric(Dielectric::new(1.5)),
                     )));
                 }
             }
@@ -256,24 +433,130 @@ pub fn create_book_1_final_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Sh
     objects.push(Shape::Sph(Sphere::new(
         Vec3::new(0.0, 1.0, 0.0),
         1.0,
-        Material::Dielectric(Dielectric {
-            index_of_refraction: 1.5,
-        }),
+        Material::Dielectric(Dielectric::new(1.5)),
     )));
     objects.push(Shape::Sph(Sphere::new(
         Vec3::new(-4.0, 1.0, 0.0),
         1.0,
         Material::Lambertian(Lambertian {
-            albedo: Vec3::new(0.4, 0.2, 0.1),
+            albedo: Vec3::new(0.4, 0.2, 0.1).into(),
         }),
     )));
     objects.push(Shape::Sph(Sphere::new(
         Vec3::new(4.0, 1.0, 0.0),
         1.0,
-        Material::Metal(Metal {
-            albedo: Vec3::new(0.7, 0.6, 0.5),
-            roughness: 0.0,
+        Material::Metal(Metal::new(Vec3::new(0.7, 0.6, 0.5).into(), 0.0)),
+    )));
+    let camera = camera.autofocus(&objects.as_slice(), (0.5, 0.5)).into();
+    (camera, objects)
+}
+
+/// Diffuse spheres bouncing up while the shutter is open from time 0 to 1.
+pub fn create_motion_blur_scene<'a>(width: f64, height: f64) -> (Camera, Vec<Shape<'a, 'a>>) {
+    let look_from = Vec3::new(13.0, 2.0, 3.0);
+    let look_at = Vec3::new(0.0, 0.0, 0.0);
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let aperture = 0.0;
+    let camera = Perspective::new(
+        look_from,
+        look_at,
+        up,
+        20.0,
+        width / height,
+        aperture,
+        (look_from - look_at).length(),
+    )
+    .with_shutter(0.0, 1.0);
+
+    let mut objects: Vec<Shape> = vec![];
+    let mut rng = Pcg64Mcg::seed_from_u64(10);
+
+    objects.push(Shape::Sph(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Material::Lambertian(Lambertian {
+            albedo: Vec3::new(0.5, 0.5, 0.5).into(),
         }),
     )));
+    for a in -6..6 {
+        for b in -6..6 {
+            let center = Vec3::new(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+            let bounce = Vec3::new(0.0, 0.5 * rng.gen::<f64>(), 0.0);
+            let track = Track::new(
+                vec![
+                    Keyframe {
+                        time: 0.0,
+                        value: center,
+                    },
+                    Keyframe {
+                        time: 1.0,
+                        value: center + bounce,
+                    },
+                ],
+                Interpolation::Linear,
+            );
+            objects.push(Shape::MovingSphere(MovingSphere::new(
+                track,
+                0.2,
+                Material::Lambertian(Lambertian {
+                    albedo: Vec3::new(
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                    )
+                    .into(),
+                }),
+                0.0..1.0,
+            )));
+        }
+    }
+    objects.push(Shape::Sph(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Material::Metal(Metal::new(Vec3::new(0.7, 0.6, 0.5).into(), 0.0)),
+    )));
+    let camera = camera.autofocus(&objects.as_slice(), (0.5, 0.5)).into();
     (camera, objects)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs};
+
+    #[test]
+    fn mesh_uses_mtl_material() {
+        let dir = temp_dir().join("raytrace_mtl_mesh");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("tri.mtl"),
+            "newmtl red\nKd 0.8 0.1 0.1\nNs 50\nNi 1.4\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tri.obj"),
+            "mtllib tri.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        let mesh = load_mesh(&dir.join("tri.obj"));
+        fs::remove_dir_all(&dir).unwrap();
+        match mesh.material {
+            Material::Principled(principled) => assert_eq!(principled.ior, 1.4f32 as f64),
+            material => panic!("expected a principled material, got {:?}", material),
+        }
+    }
+
+    #[test]
+    fn content_hash_covers_camera_and_mesh() {
+        let scene = SceneDescription::Suzanne;
+        let hash = scene.content_hash((16, 9));
+        assert_eq!(hash, scene.content_hash((16, 9)));
+        assert_ne!(hash, scene.content_hash((4, 3)));
+        assert_ne!(hash, SceneDescription::FiveSpheres.content_hash((16, 9)));
+    }
+}
@@ -0,0 +1,234 @@
+use super::protocol::{read_result, Job};
+use crate::{
+    create_scene::SceneDescription,
+    render::{Film, Tile},
+};
+use std::{
+    collections::VecDeque,
+    io::{self, BufReader, BufWriter},
+    net::{SocketAddr, TcpStream},
+    sync::{Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// Splits a render into jobs and hands them out to workers over TCP.
+///
+/// Each job covers one tile and a range of sample passes. A job whose worker
+/// fails or times out is put back in the queue and picked up by another worker.
+/// The timeout defaults to a minute; with `None` a worker that stops replying
+/// blocks the render forever. Jobs default to 16 sample passes of a 32 by 32
+/// tile, so a job stays well within the timeout even for slow scenes.
+#[derive(Clone, Debug)]
+pub struct Coordinator {
+    pub workers: Vec<SocketAddr>,
+    pub tile_size: u32,
+    pub samples_per_job: u32,
+    pub timeout: Option<Duration>,
+}
+
+struct Queue {
+    pending: VecDeque<Job>,
+    remaining: usize,
+}
+
+impl Coordinator {
+    pub fn new(workers: Vec<SocketAddr>) -> Coordinator {
+        Coordinator {
+            workers,
+            tile_size: 32,
+            samples_per_job: 16,
+            timeout: Some(Duration::from_secs(60)),
+        }
+    }
+
+    pub fn render(
+        &self,
+        scene: SceneDescription,
+        resolution: (u32, u32),
+        samples_per_pixel: u32,
+        seed: u64,
+    ) -> io::Result<Film> {
+        let step = self.samples_per_job.max(1);
+        let scene_hash = scene.content_hash(resolution);
+        let pending: VecDeque<Job> = Tile::split(resolution, self.tile_size)
+            .into_iter()
+            .flat_map(|tile| {
+                (0..samples_per_pixel)
+                    .step_by(step as usize)
+                    .map(move |start| Job {
+                        scene,
+                        scene_hash,
+                        resolution,
+                        seed,
+                        tile,
+                        samples: start..samples_per_pixel.min(start.saturating_add(step)),
+                    })
+            })
+            .collect();
+
+        let queue = Mutex::new(Queue {
+            remaining: pending.len(),
+            pending,
+        });
+        let changed = Condvar::new();
+        let film = Mutex::new(Film::new(resolution));
+
+        thread::scope(|s| {
+            for &addr in &self.workers {
+                let (queue, changed, film) = (&queue, &changed, &film);
+                s.spawn(move || {
+                    if let Err(err) = self.drive_worker(addr, queue, changed, film) {
+                        eprintln!("worker {} failed: {}", addr, err);
+                    }
+                });
+            }
+        });
+
+        match queue.into_inner().unwrap().remaining {
+            0 => Ok(film.into_inner().unwrap()),
+            n => Err(io::Error::other(format!(
+                "all workers failed with {} jobs left",
+                n
+            ))),
+        }
+    }
+
+    fn drive_worker(
+        &self,
+        addr: SocketAddr,
+        queue: &Mutex<Queue>,
+        changed: &Condvar,
+        film: &Mutex<Film>,
+    ) -> io::Result<()> {
+        let stream = match self.timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout)?,
+            None => TcpStream::connect(addr)?,
+        };
+        stream.set_read_timeout(self.timeout)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        while let Some(job) = next_job(queue, changed) {
+            let result = job
+                .write_to(&mut writer)
+                .and_then(|_| read_result(&mut reader, job.tile));
+            match result {
+                Ok(tile_film) => {
+                    film.lock().unwrap().merge_tile(job.tile, &tile_film);
+                    queue.lock().unwrap().remaining -= 1;
+                    changed.notify_all();
+                }
+                Err(err) => {
+                    queue.lock().unwrap().pending.push_back(job);
+                    changed.notify_all();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Waits for a job, returning `None` once every job is done. While jobs are in
+/// flight on other workers an idle worker keeps waiting, since a failed job is
+/// put back in the queue.
+fn next_job(queue: &Mutex<Queue>, changed: &Condvar) -> Option<Job> {
+    let mut q = queue.lock().unwrap();
+    loop {
+        if let Some(job) = q.pending.pop_front() {
+            return Some(job);
+        }
+        if q.remaining == 0 {
+            return None;
+        }
+        q = changed.wait(q).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distributed::worker::serve;
+    use crate::render::render_pass;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+
+    fn spawn_worker() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+        addr
+    }
+
+    /// Worker that accepts a job and then drops the connection.
+    fn spawn_failing_worker() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(stream).read_line(&mut line).unwrap();
+        });
+        addr
+    }
+
+    /// Worker that accepts a job and never replies.
+    fn spawn_hanging_worker() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            loop {
+                thread::park();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn distributed_render_matches_local_render() {
+        let resolution = (12, 6);
+        let (samples, seed) = (3, 11);
+        let scene = SceneDescription::FiveSpheres;
+
+        let mut coordinator =
+            Coordinator::new(vec![spawn_failing_worker(), spawn_worker(), spawn_worker()]);
+        coordinator.tile_size = 5;
+        coordinator.samples_per_job = 2;
+        let distributed = coordinator
+            .render(scene, resolution, samples, seed)
+            .unwrap();
+
+        let local = scene.with_scene(resolution, |bvh, camera| {
+            let mut film = Film::new(resolution);
+            for sample in 0..samples {
+                render_pass(&mut film, bvh, camera, seed, sample);
+            }
+            film
+        });
+
+        assert_eq!(distributed.samples, local.samples);
+        for (a, b) in distributed.pixels.iter().zip(local.pixels.iter()) {
+            assert!((*a - *b).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn hanging_worker_times_out() {
+        let mut coordinator = Coordinator::new(vec![spawn_hanging_worker()]);
+        coordinator.timeout = Some(Duration::from_millis(200));
+        let result = coordinator.render(SceneDescription::FiveSpheres, (4, 2), 1, 0);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn render_fails_without_workers() {
+        let coordinator = Coordinator::new(vec![spawn_failing_worker()]);
+        let result = coordinator.render(SceneDescription::FiveSpheres, (4, 2), 1, 0);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    create_scene::SceneDescription,
+    render::{Film, Tile},
+};
+use std::{
+    io::{self, BufRead, Write},
+    ops::Range,
+    str::{FromStr, SplitWhitespace},
+};
+
+/// Unit of work sent from the coordinator to a worker: render the sample passes
+/// `samples` for the pixels in `tile` of the named scene. The worker builds the
+/// scene itself and refuses the job if its [`SceneDescription::content_hash`]
+/// differs from `scene_hash`.
+///
+/// On the wire a job is a single line of text,
+/// `JOB <scene> <scene hash> <width> <height> <seed> <x> <y> <tile width> <tile height> <first sample> <end sample>`.
+/// The worker answers with a line `TILE <x> <y> <tile width> <tile height>`
+/// followed by the tile film in the binary format of [`Film::write_to`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Job {
+    pub scene: SceneDescription,
+    pub scene_hash: u64,
+    pub resolution: (u32, u32),
+    pub seed: u64,
+    pub tile: Tile,
+    pub samples: Range<u32>,
+}
+
+impl Job {
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "JOB {} {} {} {} {} {} {} {} {} {} {}",
+            self.scene,
+            self.scene_hash,
+            self.resolution.0,
+            self.resolution.1,
+            self.seed,
+            self.tile.x,
+            self.tile.y,
+            self.tile.width,
+            self.tile.height,
+            self.samples.start,
+            self.samples.end
+        )?;
+        w.flush()
+    }
+
+    /// Reads the next job, returning `None` once the coordinator closed the connection.
+    pub fn read_from(r: &mut impl BufRead) -> io::Result<Option<Job>> {
+        let mut line = String::new();
+        if r.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some("JOB") {
+            return Err(invalid_data("expected job"));
+        }
+        let job = Job {
+            scene: next_field(&mut fields)?,
+            scene_hash: next_field(&mut fields)?,
+            resolution: (next_field(&mut fields)?, next_field(&mut fields)?),
+            seed: next_field(&mut fields)?,
+            tile: read_tile(&mut fields)?,
+            samples: next_field(&mut fields)?..next_field(&mut fields)?,
+        };
+        Ok(Some(job))
+    }
+}
+
+pub fn write_result(w: &mut impl Write, tile: Tile, film: &Film) -> io::Result<()> {
+    writeln!(
+        w,
+        "TILE {} {} {} {}",
+        tile.x, tile.y, tile.width, tile.height
+    )?;
+    film.write_to(w)?;
+    w.flush()
+}
+
+/// Reads the film a worker returned for `tile`.
+pub fn read_result(r: &mut impl BufRead, tile: Tile) -> io::Result<Film> {
+    let mut line = String::new();
+    if r.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "worker closed the connection",
+        ));
+    }
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("TILE") || read_tile(&mut fields)? != tile {
+        return Err(invalid_data("unexpected tile"));
+    }
+    Film::read_from(r, (tile.width, tile.height))
+}
+
+fn read_tile(fields: &mut SplitWhitespace) -> io::Result<Tile> {
+    Ok(Tile {
+        x: next_field(fields)?,
+        y: next_field(fields)?,
+        width: next_field(fields)?,
+        height: next_field(fields)?,
+    })
+}
+
+fn next_field<T: FromStr>(fields: &mut SplitWhitespace) -> io::Result<T> {
+    fields
+        .next()
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| invalid_data("malformed message"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
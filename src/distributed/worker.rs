@@ -0,0 +1,166 @@
+use super::protocol::{write_result, Job};
+use crate::{
+    bvh::bvh::Bvh,
+    camera::projection::Camera,
+    render::{render_tile_pass, Film},
+};
+use std::{
+    io::{self, BufRead, BufReader, BufWriter},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+/// Accepts coordinator connections and renders the jobs they send, one thread
+/// per connection.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream) {
+                eprintln!("worker connection failed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    let mut next = read_job(&mut reader)?;
+    // The scene is kept alive for as long as consecutive jobs ask for the same
+    // scene, since building it is much more expensive than rendering a tile.
+    while let Some(first) = next {
+        let (scene, resolution) = (first.scene, first.resolution);
+        let scene_hash = scene.content_hash(resolution);
+        next = scene.with_scene(resolution, |bvh, camera| {
+            let mut job = first;
+            loop {
+                check_job(&job, scene_hash)?;
+                let film = render_job(&job, bvh, camera);
+                write_result(&mut writer, job.tile, &film)?;
+                match read_job(&mut reader)? {
+                    Some(j) if (j.scene, j.resolution) == (scene, resolution) => job = j,
+                    other => return Ok::<_, io::Error>(other),
+                }
+            }
+        })?;
+    }
+    Ok(())
+}
+
+/// Largest image side a worker accepts, which keeps pixel indices within `u32`.
+const MAX_RESOLUTION: u32 = 1 << 15;
+/// Largest tile a worker accepts, since it holds the film of a whole tile.
+const MAX_TILE_PIXELS: u64 = 1 << 22;
+
+/// Reads the next job and checks that its tile can be rendered.
+fn read_job(reader: &mut impl BufRead) -> io::Result<Option<Job>> {
+    let job = Job::read_from(reader)?;
+    if let Some(job) = &job {
+        let (width, height) = job.resolution;
+        if width > MAX_RESOLUTION || height > MAX_RESOLUTION {
+            return Err(invalid_input("resolution too large"));
+        }
+        if !job.tile.fits(job.resolution) {
+            return Err(invalid_input("tile outside of image"));
+        }
+        if job.tile.width as u64 * job.tile.height as u64 > MAX_TILE_PIXELS {
+            return Err(invalid_input("tile too large"));
+        }
+    }
+    Ok(job)
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Refuses jobs this worker cannot render as the coordinator expects.
+fn check_job(job: &Job, scene_hash: u64) -> io::Result<()> {
+    if job.scene_hash != scene_hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("scene {} differs from the coordinator's", job.scene),
+        ));
+    }
+    Ok(())
+}
+
+fn render_job(job: &Job, bvh: &Bvh, camera: &Camera) -> Film {
+    let mut film = Film::new((job.tile.width, job.tile.height));
+    for sample in job.samples.clone() {
+        render_tile_pass(
+            &mut film,
+            job.tile,
+            job.resolution,
+            bvh,
+            camera,
+            job.seed,
+            sample,
+        );
+    }
+    film
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_scene::SceneDescription;
+    use crate::distributed::protocol::read_result;
+    use crate::render::Tile;
+
+    /// Sends `job` to a new worker and reads its answer.
+    fn send(job: &Job) -> io::Result<Film> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+        let stream = TcpStream::connect(addr)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        job.write_to(&mut BufWriter::new(stream))?;
+        read_result(&mut reader, job.tile)
+    }
+
+    fn job() -> Job {
+        let scene = SceneDescription::FiveSpheres;
+        let resolution = (4, 2);
+        Job {
+            scene,
+            scene_hash: scene.content_hash(resolution),
+            resolution,
+            seed: 0,
+            tile: Tile::full(resolution),
+            samples: 0..1,
+        }
+    }
+
+    #[test]
+    fn renders_job() {
+        let film = send(&job()).unwrap();
+        assert_eq!(film.samples, vec![1; 8]);
+    }
+
+    #[test]
+    fn refuses_tile_outside_of_image() {
+        let mut job = job();
+        job.tile.y = u32::MAX;
+        assert!(send(&job).is_err());
+    }
+
+    #[test]
+    fn refuses_huge_tile() {
+        let mut job = job();
+        job.resolution = (MAX_RESOLUTION, MAX_RESOLUTION);
+        job.scene_hash = job.scene.content_hash(job.resolution);
+        job.tile = Tile::full(job.resolution);
+        assert!(send(&job).is_err());
+    }
+
+    #[test]
+    fn refuses_different_scene() {
+        let mut job = job();
+        job.scene_hash ^= 1;
+        assert!(send(&job).is_err());
+    }
+}
@@ -1,10 +1,20 @@
 #![forbid(unsafe_code)]
 
+pub mod animation;
 pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
+pub mod compare;
 pub mod create_scene;
+pub mod distributed;
+pub mod linear_image;
 pub mod material;
+pub mod mlt;
+pub mod random;
 pub mod ray;
+pub mod render;
 pub mod shape;
+pub mod texture;
+pub mod transform;
 pub mod utility;
 pub mod vec3;
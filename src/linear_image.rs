@@ -0,0 +1,101 @@
+use crate::{render::Film, vec3::Vec3};
+use image::{DynamicImage, ImageResult};
+use std::path::Path;
+
+/// Image with linear RGB pixels, rows stored top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec3>,
+}
+
+impl LinearImage {
+    /// Loads an image. Floating point formats such as OpenEXR and Radiance HDR
+    /// are taken to be linear, all other formats are decoded as sRGB at their
+    /// full bit depth.
+    pub fn load(path: &Path) -> ImageResult<LinearImage> {
+        let img = image::open(path)?;
+        let linear = matches!(
+            img,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        let image = LinearImage::from_dynamic(img);
+        Ok(match linear {
+            true => image,
+            false => image.map(|p| {
+                Vec3::new(
+                    srgb_to_linear(p.x),
+                    srgb_to_linear(p.y),
+                    srgb_to_linear(p.z),
+                )
+            }),
+        })
+    }
+
+    /// Loads an image holding data rather than color, such as a normal map or
+    /// an aperture mask, without decoding it as sRGB.
+    pub fn load_data(path: &Path) -> ImageResult<LinearImage> {
+        Ok(LinearImage::from_dynamic(image::open(path)?))
+    }
+
+    fn from_dynamic(img: DynamicImage) -> LinearImage {
+        let img = img.to_rgb32f();
+        LinearImage {
+            width: img.width(),
+            height: img.height(),
+            pixels: img
+                .pixels()
+                .map(|p| Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect(),
+        }
+    }
+
+    pub fn from_film(film: &Film) -> LinearImage {
+        LinearImage {
+            width: film.width,
+            height: film.height,
+            pixels: (0..film.pixels.len()).map(|i| film.mean(i)).collect(),
+        }
+    }
+
+    /// Applies `f` to every pixel.
+    pub fn map(&self, f: impl Fn(Vec3) -> Vec3) -> LinearImage {
+        LinearImage {
+            pixels: self.pixels.iter().map(|p| f(*p)).collect(),
+            ..*self
+        }
+    }
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use image::{ImageBuffer, Rgb};
+    use std::{env::temp_dir, fs};
+
+    #[test]
+    fn keeps_sixteen_bit_precision() {
+        // 1000 lies between two 8 bit levels.
+        let img = ImageBuffer::from_pixel(2, 1, Rgb([1000u16, 0, 65535]));
+        let path = temp_dir().join("raytrace_linear_image_16bit.png");
+        img.save(&path).unwrap();
+        let color = LinearImage::load(&path).unwrap();
+        let data = LinearImage::load_data(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((color.width, color.height), (2, 1));
+        assert_approx_eq!(color.pixels[1].x, srgb_to_linear(1000.0 / 65535.0), 1e-7);
+        assert_eq!(color.pixels[1].z, 1.0);
+        assert_approx_eq!(data.pixels[0].x, 1000.0 / 65535.0, 1e-7);
+    }
+}
@@ -1,103 +1,351 @@
-use rand::Rng;
-use rayon::prelude::*;
-use raytrace_rust::bvh::bvh::split_method::Middle;
 use raytrace_rust::bvh::bvh::Bvh;
-use raytrace_rust::camera::Camera;
-use raytrace_rust::create_scene::create_suzanne_scene;
-use raytrace_rust::material::scatterable::Scatterable;
-use raytrace_rust::ray::Ray;
-use raytrace_rust::shape::hittable::Hittable;
-use raytrace_rust::shape::hittable::Shape;
-use raytrace_rust::utility::lerp;
-use raytrace_rust::vec3::Vec3;
-use std::f64::INFINITY;
-use std::path::Path;
-use std::time::Instant;
+use raytrace_rust::camera::exposure::{auto_exposure, Exposure};
+use raytrace_rust::camera::projection::Camera;
+use raytrace_rust::camera::stereo::{Stereo, StereoLayout, StereoMode};
+use raytrace_rust::checkpoint::Checkpoint;
+use raytrace_rust::create_scene::{create_suzanne_turntable, SceneDescription};
+use raytrace_rust::distributed::coordinator::Coordinator;
+use raytrace_rust::distributed::worker::serve;
+use raytrace_rust::mlt::Mlt;
+use raytrace_rust::render::{composite, render_crop, save_png, Film, Tile};
+use std::env;
+use std::net::{SocketAddr, TcpListener};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+struct Options {
+    scene: SceneDescription,
+    samples: u32,
+    output: PathBuf,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: Duration,
+    worker: Option<SocketAddr>,
+    workers: Vec<SocketAddr>,
+    frames: Option<Range<u32>>,
+    crop: Option<Tile>,
+    crop_mode: CropMode,
+    mlt: bool,
+    auto_exposure: bool,
+    exposure: Option<Exposure>,
+    stereo: Option<StereoLayout>,
+}
+
+/// What to write when only a crop window is rendered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CropMode {
+    /// Only the pixels inside the window.
+    Cropped,
+    /// The full image, black outside the window.
+    Full,
+    /// The window pasted into the existing output image.
+    Composite,
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        scene: SceneDescription::Suzanne,
+        samples: 100,
+        output: PathBuf::from("./renders/img.png"),
+        checkpoint: None,
+        checkpoint_interval: Duration::from_secs(60),
+        worker: None,
+        workers: vec![],
+        frames: None,
+        crop: None,
+        crop_mode: CropMode::Full,
+        mlt: false,
+        auto_exposure: false,
+        exposure: None,
+        stereo: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| panic!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--scene" => options.scene = value().parse().expect("invalid scene"),
+            "--samples" => options.samples = value().parse().expect("invalid sample count"),
+            "--output" => options.output = PathBuf::from(value()),
+            "--checkpoint" => options.checkpoint = Some(PathBuf::from(value())),
+            "--checkpoint-interval" => {
+                options.checkpoint_interval =
+                    Duration::from_secs(value().parse().expect("invalid checkpoint interval"))
+            }
+            "--worker" => options.worker = Some(value().parse().expect("invalid address")),
+            "--workers" => {
+                options.workers = value()
+                    .split(',')
+                    .map(|addr| addr.parse().expect("invalid address"))
+                    .collect()
+            }
+            "--frames" => {
+                let value = value();
+                let (start, end) = value
+                    .split_once("..")
+                    .expect("expected frames as start..end");
+                options.frames =
+                    Some(start.parse().expect("invalid frame")..end.parse().expect("invalid frame"))
+            }
+            "--crop" => {
+                let values: Vec<u32> = value()
+                    .split(',')
+                    .map(|v| v.parse().expect("invalid crop window"))
+                    .collect();
+                let [x, y, width, height] = values[..] else {
+                    panic!("expected crop window as x,y,width,height")
+                };
+                options.crop = Some(Tile {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+            "--crop-mode" => {
+                options.crop_mode = match value().as_str() {
+                    "cropped" => CropMode::Cropped,
+                    "full" => CropMode::Full,
+                    "composite" => CropMode::Composite,
+                    mode => panic!("unknown crop mode: {}", mode),
+                }
+            }
+            "--mlt" => options.mlt = true,
+            "--auto-exposure" => options.auto_exposure = true,
+            "--exposure" => {
+                let values: Vec<f64> = value()
+                    .split(',')
+                    .map(|v| v.parse().expect("invalid exposure"))
+                    .collect();
+                let [f_number, shutter_time, iso] = values[..] else {
+                    panic!("expected exposure as f-number,shutter time,iso")
+                };
+                options.exposure = Some(Exposure {
+                    f_number,
+                    shutter_time,
+                    iso,
+                })
+            }
+            "--stereo" => {
+                options.stereo = match value().as_str() {
+                    "side-by-side" => Some(StereoLayout::SideBySide),
+                    "over-under" => Some(StereoLayout::OverUnder),
+                    layout => panic!("unknown stereo layout: {}", layout),
+                }
+            }
+            _ => panic!("unknown argument: {}", arg),
+        }
+    }
+    options
+}
 
 fn main() {
+    let options = parse_args();
+    if let Some(addr) = options.worker {
+        serve(TcpListener::bind(addr).unwrap()).unwrap();
+        return;
+    }
+
     let start = Instant::now();
-    let resolution = (400, 200);
-    let samples = 100;
+    let view = (400, 200);
+    let resolution = options
+        .stereo
+        .map_or(view, |layout| layout.image_resolution(view));
+
+    if let Some(frames) = options.frames {
+        assert!(
+            options.stereo.is_none(),
+            "animations are not rendered in stereo"
+        );
+        let animation = create_suzanne_turntable(resolution.0 as f64, resolution.1 as f64, 2.0);
+        let pattern = options.output.to_string_lossy().into_owned();
+        animation.render_frames(frames, resolution, options.samples, |frame| {
+            PathBuf::from(frame_path(&pattern, frame))
+        });
+        println!("Time: {}", start.elapsed().as_secs_f64());
+        return;
+    }
 
-    let (camera, mesh, mut world) = create_suzanne_scene(resolution.0 as f64, resolution.1 as f64);
-    world.extend(mesh.triangles().map(|tri| Shape::Triangle(tri)));
-    let bvh = Bvh::build(&mut world, Middle);
+    if let Some(crop) = options.crop {
+        let mut film = with_scene(&options, view, |bvh, camera| {
+            render_crop(
+                resolution,
+                crop,
+                options.samples,
+                rand::random(),
+                bvh,
+                camera,
+            )
+        });
+        // Automatic exposure is left out, since it would differ between crops.
+        if let Some(exposure) = options.exposure {
+            film.expose(exposure.scale());
+        }
+        match options.crop_mode {
+            CropMode::Cropped => {
+                save_png(&options.output, film.resolution(), &film.to_srgb_bytes())
+            }
+            CropMode::Full => {
+                let mut full = Film::new(resolution);
+                full.merge_tile(crop, &film);
+                save_png(&options.output, resolution, &full.to_srgb_bytes());
+            }
+            CropMode::Composite => {
+                let mut image = image::open(&options.output).unwrap().to_rgb8();
+                assert_eq!(
+                    image.dimensions(),
+                    resolution,
+                    "image resolution does not match"
+                );
+                composite(&mut image, resolution, &film.to_srgb_bytes(), crop);
+                save_png(&options.output, resolution, &image);
+            }
+        }
+        println!("Time: {}", start.elapsed().as_secs_f64());
+        return;
+    }
 
-    let buffer = render(resolution, samples, bvh, camera);
+    let mut film = if options.mlt {
+        // With Metropolis sampling, the sample count is the number of mutations
+        // per pixel.
+        let mlt = Mlt {
+            mutations_per_pixel: options.samples,
+            ..Mlt::default()
+        };
+        with_scene(&options, view, |bvh, camera| {
+            mlt.render(resolution, bvh, camera, rand::random())
+        })
+    } else if !options.workers.is_empty() {
+        // Workers build the scene by name, and with it the camera.
+        assert!(
+            options.stereo.is_none(),
+            "stereo renders are not distributed"
+        );
+        Coordinator::new(options.workers.clone())
+            .render(options.scene, resolution, options.samples, rand::random())
+            .unwrap()
+    } else {
+        with_scene(&options, view, |bvh, camera| match &options.checkpoint {
+            Some(path) => {
+                let mut checkpoint = match Path::exists(path) {
+                    true => Checkpoint::load(path).unwrap(),
+                    false => Checkpoint::new(resolution, rand::random()),
+                };
+                assert_eq!(
+                    checkpoint.film.resolution(),
+                    resolution,
+                    "checkpoint resolution does not match"
+                );
+                checkpoint
+                    .render(
+                        bvh,
+                        camera,
+                        options.samples,
+                        path,
+                        options.checkpoint_interval,
+                    )
+                    .unwrap();
+                checkpoint.film
+            }
+            None => render_crop(
+                resolution,
+                Tile::full(resolution),
+                options.samples,
+                rand::random(),
+                bvh,
+                camera,
+            ),
+        })
+    };
+    // Both scale every pixel alike, so they are applied to the finished film
+    // however it was rendered.
+    if options.auto_exposure {
+        film.expose(auto_exposure(&film));
+    } else if let Some(exposure) = options.exposure {
+        film.expose(exposure.scale());
+    }
     let duration = start.elapsed();
     println!("Time: {}", duration.as_secs_f64());
-    let path = Path::new("./renders/img.png");
-    save_png(path, resolution, &buffer);
+    save_png(&options.output, resolution, &film.to_srgb_bytes());
 }
 
-fn render(
-    resolution: (u32, u32),
-    samples_per_pixel: u32,
-    scene: impl Hittable + std::marker::Sync,
-    camera: Camera,
-) -> Vec<u8> {
-    let (width, height) = resolution;
-    let w = width as f64;
-    let h = height as f64;
-    let s = samples_per_pixel as f64;
-
-    let pixel_iter = (0..height).into_par_iter().rev().flat_map(|j| {
-        (0..width)
-            .into_par_iter()
-            .map(move |i| (i as f64, j as f64))
-    });
-
-    pixel_iter
-        .flat_map(|(i, j)| {
-            let mut rng = rand::thread_rng();
-            let mut col = Vec3::origin();
-            for _ in 0..samples_per_pixel {
-                let u = (i + rng.gen::<f64>()) / w;
-                let v = (j + rng.gen::<f64>()) / h;
-                let r = camera.get_ray((u, v));
-                col += color(&r, &scene, 0) * 1.0 / s;
+/// Builds the scene with its camera seeing `view`, and passes its bvh and
+/// camera to `f`. For stereo renders the camera becomes the left and right
+/// eye of a rig, each seeing `view` in its half of the image.
+fn with_scene<R>(options: &Options, view: (u32, u32), f: impl FnOnce(&Bvh, &Camera) -> R) -> R {
+    // Scene units are taken to be meters.
+    let interocular = 0.064;
+    options
+        .scene
+        .with_scene(view, |bvh, camera| match options.stereo {
+            None => f(bvh, camera),
+            Some(layout) => {
+                let Camera::Perspective(camera) = camera else {
+                    panic!("stereo renders need a perspective camera")
+                };
+                let rig =
+                    Stereo::perspective(camera.clone(), interocular, StereoMode::Parallel, layout);
+                f(bvh, &rig.into())
             }
-            to_srgb_bytes(col)
         })
-        .collect()
 }
 
-fn color<T: Hittable>(ray: &Ray, scene: &T, depth: i32) -> Vec3 {
-    let max_depth = 50;
-    if depth >= max_depth {
-        return Vec3::origin();
+/// Replaces the last run of `#` in `pattern` with the zero padded frame number,
+/// or appends the number before the extension if there is none.
+fn frame_path(pattern: &str, frame: u32) -> String {
+    match pattern.rfind('#') {
+        Some(end) => {
+            let start = pattern[..end].trim_end_matches('#').len();
+            let width = end + 1 - start;
+            format!(
+                "{}{:0width$}{}",
+                &pattern[..start],
+                frame,
+                &pattern[end + 1..],
+                width = width
+            )
+        }
+        None => {
+            let path = Path::new(pattern);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match path.extension() {
+                Some(ext) => format!("{}_{:04}.{}", stem, frame, ext.to_string_lossy()),
+                None => format!("{}_{:04}", stem, frame),
+            };
+            path.with_file_name(file_name)
+                .to_string_lossy()
+                .into_owned()
+        }
     }
-    match scene.hit(ray, 0.0001..INFINITY) {
-        Some(rec) => match rec.material.scatter(ray, rec) {
-            Some((scattered, attenuation)) => {
-                return attenuation * color(&scattered, scene, depth + 1);
-            }
-            None => return Vec3::origin(),
-        },
-        None => (),
-    };
-
-    let dir = ray.direction.unitize();
-    let t = 0.5 * dir.y + 1.0;
-    lerp(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.5, 0.7, 1.0), t)
 }
 
-fn to_srgb_bytes(v: Vec3) -> [u8; 3] {
-    [
-        (linear_srgb_transfer_function(v.x) * 255.99) as u8,
-        (linear_srgb_transfer_function(v.y) * 255.99) as u8,
-        (linear_srgb_transfer_function(v.z) * 255.99) as u8,
-    ]
-}
+#[cfg(test)]
+mod tests {
+    use super::frame_path;
 
-fn linear_srgb_transfer_function(linear: f64) -> f64 {
-    if linear < 0.0031308 {
-        linear * 12.92
-    } else {
-        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    #[test]
+    fn frame_path_replaces_hashes() {
+        assert_eq!(frame_path("out/frame_###.png", 7), "out/frame_007.png");
     }
-}
 
-fn save_png(path: &Path, size: (u32, u32), buffer: &[u8]) {
-    image::save_buffer(path, buffer, size.0, size.1, image::ColorType::Rgb8).unwrap()
+    #[test]
+    fn frame_path_appends_before_extension() {
+        assert_eq!(frame_path("out/frame.png", 7), "out/frame_0007.png");
+    }
+
+    #[test]
+    fn frame_path_ignores_dots_in_directories() {
+        assert_eq!(frame_path("renders.v2/frame", 7), "renders.v2/frame_0007");
+        assert_eq!(
+            frame_path("renders.v2/frame.png", 7),
+            "renders.v2/frame_0007.png"
+        );
+    }
+
+    #[test]
+    fn frame_path_without_extension() {
+        assert_eq!(frame_path("frame", 12), "frame_0012");
+    }
 }
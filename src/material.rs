@@ -1,4 +1,33 @@
 pub mod dielectric;
+pub mod fresnel;
 pub mod lambertian;
+pub mod medium;
 pub mod metal;
+pub mod microfacet;
+pub mod normal_mapped;
+pub mod principled;
 pub mod scatterable;
+pub mod subsurface;
+pub mod thin_film;
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::{material::scatterable::Material, shape::hittable::HitRecord, vec3::Vec3};
+
+    /// Hit at the origin for testing materials. The shading and geometric
+    /// normals are both `normal`, and `dpdv` completes `dpdu` and the normal to
+    /// a right handed frame.
+    pub fn record(material: &Material, normal: Vec3, uv: (f64, f64), dpdu: Vec3) -> HitRecord<'_> {
+        HitRecord {
+            t: 1.0,
+            point: Vec3::origin(),
+            normal,
+            geometric_normal: normal,
+            uv,
+            barycentric: (0.0, 0.0),
+            dpdu,
+            dpdv: Vec3::cross(normal, dpdu),
+            material,
+        }
+    }
+}
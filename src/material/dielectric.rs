@@ -1,48 +1,327 @@
-use rand::Rng;
-
-use super::scatterable::Scatterable;
-use crate::{ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
+use super::{
+    fresnel,
+    medium::Medium,
+    microfacet::{ShadingFrame, TrowbridgeReitz},
+    scatterable::Scatterable,
+    thin_film::ThinFilm,
+};
+use crate::{random, ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
 
+/// Interface to a transparent medium such as glass, with the outside on the
+/// side the normal points to. Rough surfaces reflect and refract off the
+/// microfacets of a Trowbridge-Reitz (GGX) distribution (Walter et al. 2007).
 #[derive(Clone, Debug)]
 pub struct Dielectric {
+    /// Index of refraction of the inside relative to the outside.
     pub index_of_refraction: f64,
+    /// Perceptual roughness in `[0, 1]`, zero being perfectly smooth.
+    pub roughness: f64,
+    /// Absorption coefficient of the inside, tinting light travelling through
+    /// it.
+    pub absorption: Vec3,
+    /// Priority of the inside where it overlaps other media, see
+    /// [`MediumStack`](super::medium::MediumStack).
+    pub priority: u32,
+    /// Coating on the outside of the surface.
+    pub thin_film: Option<ThinFilm>,
+}
+
+impl Dielectric {
+    pub fn new(index_of_refraction: f64) -> Dielectric {
+        Dielectric {
+            index_of_refraction,
+            roughness: 0.0,
+            absorption: Vec3::origin(),
+            priority: 0,
+            thin_film: None,
+        }
+    }
+
+    /// Frosts the surface with the given perceptual roughness.
+    pub fn with_roughness(self, roughness: f64) -> Dielectric {
+        Dielectric { roughness, ..self }
+    }
+
+    /// Absorbs light inside the medium so that white light turns into `color`
+    /// after travelling `distance`.
+    pub fn with_transmittance(self, color: Vec3, distance: f64) -> Dielectric {
+        Dielectric {
+            absorption: Medium::absorption_from_transmittance(color, distance),
+            ..self
+        }
+    }
+
+    /// Lets the inside displace overlapping media of lower priority.
+    pub fn with_priority(self, priority: u32) -> Dielectric {
+        Dielectric { priority, ..self }
+    }
+
+    /// Coats the surface with a thin film.
+    pub fn with_thin_film(self, thin_film: ThinFilm) -> Dielectric {
+        Dielectric {
+            thin_film: Some(thin_film),
+            ..self
+        }
+    }
+
+    /// Reflectance at `record` for light arriving at an angle with cosine
+    /// `cos_i` to the outward normal, where the inside has the relative index
+    /// of refraction `eta`.
+    fn reflectance(&self, record: &HitRecord, cos_i: f64, eta: f64) -> Vec3 {
+        match &self.thin_film {
+            Some(film) => {
+                // The film index is absolute, so the media on both sides are
+                // too. The medium outside is the one `eta` is relative to.
+                let inside = self.index_of_refraction;
+                let outside = inside / eta;
+                let (from, to) = match cos_i < 0.0 {
+                    true => (inside, outside),
+                    false => (outside, inside),
+                };
+                let to = Vec3::new(to, to, to);
+                film.reflectance(record, cos_i.abs(), from, to, Vec3::origin())
+            }
+            None => {
+                let r = fresnel::dielectric(cos_i, eta);
+                Vec3::new(r, r, r)
+            }
+        }
+    }
+
+    /// BSDF times the cosine of `wi` and the density of sampling `wi`, for the
+    /// directions `wo` and `wi` at `record` in its shading frame, if the surface
+    /// is rough. The inside has the relative index of refraction `eta`.
+    pub(crate) fn evaluate(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> (Vec3, f64) {
+        let zero = (Vec3::origin(), 0.0);
+        let distribution = TrowbridgeReitz::new(self.roughness);
+        if distribution.is_smooth() || wo.z == 0.0 || wi.z == 0.0 {
+            return zero;
+        }
+        let reflect = wo.z * wi.z > 0.0;
+        let etap = match (reflect, wo.z > 0.0) {
+            (true, _) => 1.0,
+            (false, true) => eta,
+            (false, false) => 1.0 / eta,
+        };
+
+        // Generalized half vector, the microfacet normal scattering wo into wi.
+        let wm = wi * etap + wo;
+        if wm.length_sq() == 0.0 {
+            return zero;
+        }
+        let wm = match wm.z < 0.0 {
+            true => -wm.unitize(),
+            false => wm.unitize(),
+        };
+        if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0 {
+            return zero;
+        }
+
+        let reflectance = self.reflectance(record, wo.dot(wm), eta);
+        let p_reflect = reflection_probability(reflectance);
+        let d = distribution.d(wm);
+        let g = distribution.g(wo, wi);
+        let visible = distribution.pdf(wo, wm);
+        match reflect {
+            true => (
+                d * g * reflectance / (4.0 * wo.z.abs()),
+                visible / (4.0 * wo.dot(wm).abs()) * p_reflect,
+            ),
+            false => {
+                let denom = (wi.dot(wm) + wo.dot(wm) / etap).powi(2);
+                let f = d * g * (wi.dot(wm) * wo.dot(wm)).abs() / (wo.z.abs() * denom);
+                (
+                    (1.0 - reflectance) * f / (etap * etap),
+                    visible * wi.dot(wm).abs() / denom * (1.0 - p_reflect),
+                )
+            }
+        }
+    }
 }
 
 impl Scatterable for Dielectric {
     fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
-        let (ior_ratio, normal) = if front_face(ray.direction, record.normal) {
-            (self.index_of_refraction, -record.normal)
-        } else {
-            (1.0 / self.index_of_refraction, record.normal)
-        };
+        self.scatter_between(ray, record, self.index_of_refraction)
+    }
+
+    fn scatter_between(&self, ray: &Ray, record: HitRecord, eta: f64) -> Option<(Ray, Vec3)> {
+        let frame = ShadingFrame::new(&record);
+        let wo = frame.to_local(-ray.direction.unitize());
+        if wo.z == 0.0 {
+            return None;
+        }
 
-        let cos_theta = -Vec3::dot(ray.direction, normal);
+        let distribution = TrowbridgeReitz::new(self.roughness);
+        let wm = match distribution.is_smooth() {
+            true => Vec3::new(0.0, 0.0, 1.0),
+            false => distribution.sample_visible(wo, (random::uniform(), random::uniform())),
+        };
 
-        let scattered = if schlick(cos_theta, ior_ratio) > rand::thread_rng().gen::<f64>() {
-            Vec3::reflect(&ray.direction, normal)
+        let reflectance = self.reflectance(&record, wo.dot(wm), eta);
+        let p_reflect = reflection_probability(reflectance);
+        let (wi, scale) = if random::uniform() < p_reflect {
+            ((-wo).reflect(wm), reflectance / p_reflect)
         } else {
-            match ray.direction.refract(normal, ior_ratio) {
-                Some(refracted) => refracted,
-                None => Vec3::reflect(&ray.direction, normal),
-            }
+            let (normal, etap) = match wo.dot(wm) < 0.0 {
+                true => (-wm, 1.0 / eta),
+                false => (wm, eta),
+            };
+            let wi = (-wo).refract(normal, 1.0 / etap)?;
+            // Radiance is compressed into a smaller solid angle when entering
+            // the denser medium, and spreads out again when leaving it.
+            (wi, (1.0 - reflectance) / (1.0 - p_reflect) / (etap * etap))
+        };
+        let reflected = wi.z * wo.z > 0.0;
+        if wi.z == 0.0 || reflected != (wi.dot(wm) * wo.dot(wm) > 0.0) {
+            return None;
+        }
+
+        // The distribution of visible normals and the Fresnel choice between the
+        // lobes cancel all but the masking of the scattered direction.
+        let masking = match distribution.is_smooth() {
+            true => 1.0,
+            false => distribution.g(wo, wi) / distribution.g1(wo),
         };
+        let scattered = Ray::new(record.point, frame.from_local(wi), ray.time);
+        Some((scattered, scale * masking))
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.eval_between(record, wo, wi, self.index_of_refraction)
+    }
+
+    fn pdf(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.pdf_between(record, wo, wi, self.index_of_refraction)
+    }
+
+    fn eval_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> Vec3 {
+        let frame = ShadingFrame::new(record);
+        self.evaluate(record, frame.to_local(wo), frame.to_local(wi), eta)
+            .0
+    }
+
+    fn pdf_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> f64 {
+        let frame = ShadingFrame::new(record);
+        self.evaluate(record, frame.to_local(wo), frame.to_local(wi), eta)
+            .1
+    }
 
-        let attenuation = Vec3::new(1.0, 1.0, 1.0);
-        Some((
-            Ray {
-                origin: record.point,
-                direction: scattered,
-            },
-            attenuation,
-        ))
+    fn medium(&self) -> Option<Medium> {
+        Some(Medium {
+            absorption: self.absorption,
+            scattering: Vec3::origin(),
+            ior: self.index_of_refraction,
+            priority: self.priority,
+        })
     }
 }
 
-fn schlick(cosine: f64, ior_ratio: f64) -> f64 {
-    let r0 = ((1.0 - ior_ratio) / (1.0 + ior_ratio)).powi(2);
-    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+/// Probability of sampling reflection rather than transmission for the
+/// reflectance of every color channel.
+fn reflection_probability(reflectance: Vec3) -> f64 {
+    (reflectance.x + reflectance.y + reflectance.z) / 3.0
 }
 
-fn front_face(direction: Vec3, normal: Vec3) -> bool {
-    Vec3::dot(direction, normal) > 0.0
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{scatterable::Material, testing::record};
+    use assert_approx_eq::assert_approx_eq;
+    use itertools::Itertools;
+
+    #[test]
+    fn sampling_matches_evaluation() {
+        random::seed(11);
+        let rough = Dielectric::new(1.5).with_roughness(0.4);
+        let coated = rough
+            .clone()
+            .with_thin_film(ThinFilm::new(Vec3::new(300.0, 300.0, 300.0).into(), 1.33));
+        let directions = [Vec3::new(0.6, 0.2, -1.0), Vec3::new(0.3, -0.2, 1.0)];
+        // Both in air and in water, where the index is relative to the water.
+        let etas = [1.5, 1.5 / 1.33];
+        for ((glass, direction), eta) in [rough, coated]
+            .iter()
+            .cartesian_product(directions)
+            .cartesian_product(etas)
+        {
+            let material = Material::Dielectric(glass.clone());
+            let rec = record(
+                &material,
+                Vec3::new(0.0, 0.0, 1.0),
+                (0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            );
+            let ray = Ray::new(-direction, direction, 0.0);
+            let wo = -direction.unitize();
+            let (mut reflected, mut transmitted) = (0, 0);
+            for _ in 0..200 {
+                if let Some((scattered, weight)) = glass.scatter_between(&ray, rec, eta) {
+                    let wi = scattered.direction.unitize();
+                    match wi.z * wo.z > 0.0 {
+                        true => reflected += 1,
+                        false => transmitted += 1,
+                    }
+                    let expected = glass.eval_between(&rec, wo, wi, eta)
+                        / glass.pdf_between(&rec, wo, wi, eta);
+                    assert_approx_eq!((weight - expected).length(), 0.0, 1e-6);
+                }
+            }
+            assert!(reflected > 0 && transmitted > 0);
+        }
+    }
+
+    #[test]
+    fn smooth_glass_follows_snell() {
+        random::seed(12);
+        let glass = Dielectric::new(1.5);
+        let material = Material::Dielectric(glass.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let direction = Vec3::new(1.0, 0.0, -1.0).unitize();
+        let ray = Ray::new(-direction, direction, 0.0);
+        let mut transmitted = 0;
+        for _ in 0..1000 {
+            let (scattered, weight) = glass.scatter(&ray, rec).unwrap();
+            let wi = scattered.direction.unitize();
+            match wi.z < 0.0 {
+                true => {
+                    transmitted += 1;
+                    assert_approx_eq!(direction.x, 1.5 * wi.x);
+                    assert_approx_eq!(weight.x, 1.0 / 2.25);
+                }
+                false => assert_approx_eq!((wi - Vec3::new(1.0, 0.0, 1.0).unitize()).length(), 0.0),
+            }
+        }
+        let expected = 1.0 - fresnel::dielectric(direction.z.abs(), 1.5);
+        assert_approx_eq!(transmitted as f64 / 1000.0, expected, 0.03);
+
+        // Past the critical angle light leaving the glass is always reflected.
+        let inside = Ray::new(Vec3::origin(), Vec3::new(1.0, 0.0, 0.5), 0.0);
+        let (scattered, _) = glass.scatter(&inside, rec).unwrap();
+        assert!(scattered.direction.z < 0.0);
+    }
+
+    #[test]
+    fn film_is_relative_to_outside_medium() {
+        // A film matching the water around the glass is just more water.
+        let glass = Dielectric::new(1.5)
+            .with_thin_film(ThinFilm::new(Vec3::new(300.0, 300.0, 300.0).into(), 1.33));
+        let material = Material::Dielectric(glass.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let eta = 1.5 / 1.33;
+        for cos_i in [0.9, 0.4, -0.9, -0.4] {
+            let r = glass.reflectance(&rec, cos_i, eta);
+            let expected = fresnel::dielectric(cos_i, eta);
+            assert_approx_eq!((r - Vec3::new(expected, expected, expected)).length(), 0.0);
+        }
+    }
 }
@@ -0,0 +1,83 @@
+use crate::vec3::Vec3;
+
+/// Unpolarized Fresnel reflectance of a dielectric interface with the relative
+/// index of refraction `eta` of the inside to the outside, for light arriving
+/// at an angle with cosine `cos_i` to the outward normal. Negative cosines are
+/// light arriving from the inside.
+pub fn dielectric(cos_i: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = match cos_i < 0.0 {
+        true => (-cos_i, 1.0 / eta),
+        false => (cos_i, eta),
+    };
+    let cos_i = cos_i.min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// Weight of the white reflectance at grazing angles in Schlick's approximation
+/// of the Fresnel reflectance for light at an angle with cosine `cos_i`.
+pub fn schlick_weight(cos_i: f64) -> f64 {
+    (1.0 - cos_i).clamp(0.0, 1.0).powi(5)
+}
+
+/// Unpolarized Fresnel reflectance of a conductor with the complex index of
+/// refraction `eta + i k` relative to the outside, for light arriving at an
+/// angle with cosine `cos_i` to the normal.
+pub fn conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let (eta2, k2) = (eta * eta, k * k);
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+/// [`conductor`] for every color channel.
+pub fn conductor_rgb(cos_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    Vec3::new(
+        conductor(cos_i, eta.x, k.x),
+        conductor(cos_i, eta.y, k.y),
+        conductor(cos_i, eta.z, k.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn dielectric_limits() {
+        assert_approx_eq!(dielectric(1.0, 1.5), 0.04);
+        assert_approx_eq!(dielectric(-1.0, 1.5), 0.04);
+        assert_approx_eq!(dielectric(0.0, 1.5), 1.0);
+        assert_approx_eq!(dielectric(0.7, 1.0), 0.0);
+        // Total internal reflection past the critical angle inside glass.
+        assert_eq!(dielectric(-0.5, 1.5), 1.0);
+        assert!(dielectric(-0.9, 1.5) < 0.1);
+    }
+
+    #[test]
+    fn conductor_limits() {
+        let (eta, k) = (0.2, 3.9);
+        let normal = ((eta - 1.0) * (eta - 1.0) + k * k) / ((eta + 1.0) * (eta + 1.0) + k * k);
+        assert_approx_eq!(conductor(1.0, eta, k), normal);
+        assert_approx_eq!(conductor(0.0, eta, k), 1.0);
+        assert_approx_eq!(conductor(0.3, 0.0, 1.0), 1.0);
+    }
+}
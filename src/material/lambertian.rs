@@ -1,15 +1,71 @@
 use super::scatterable::Scatterable;
-use crate::{ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
+use crate::{
+    ray::Ray,
+    shape::hittable::HitRecord,
+    texture::texture_kind::{Texture, TextureKind},
+    vec3::Vec3,
+};
+use std::f64::consts::PI;
 
 #[derive(Clone, Debug)]
 pub struct Lambertian {
-    pub albedo: Vec3,
+    pub albedo: TextureKind,
 }
 
 impl Scatterable for Lambertian {
-    fn scatter(&self, _ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
         let target = record.point + record.normal + Vec3::random_unit_vector();
-        let scattered = Ray::new(record.point, target - record.point);
-        Some((scattered, self.albedo))
+        let scattered = Ray::new(record.point, target - record.point, ray.time);
+        // A shading normal tilted by a normal map can send light into the
+        // surface, which is absorbed instead.
+        if scattered.direction.dot(record.geometric_normal) <= 0.0 {
+            return None;
+        }
+        Some((scattered, self.albedo.value(record.uv, record.point)))
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.albedo.value(record.uv, record.point) * self.pdf(record, wo, wi)
+    }
+
+    fn pdf(&self, record: &HitRecord, _wo: Vec3, wi: Vec3) -> f64 {
+        match record.geometric_normal.dot(wi) > 0.0 {
+            true => record.normal.dot(wi).max(0.0) / PI,
+            false => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{scatterable::Material, testing::record},
+        random,
+    };
+
+    #[test]
+    fn stays_above_geometric_surface() {
+        random::seed(51);
+        let material = Material::Lambertian(Lambertian {
+            albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+        });
+        let mut rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        rec.normal = Vec3::new(1.0, 0.0, 0.2).unitize();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let below = Vec3::new(1.0, 0.0, -0.1).unitize();
+        assert!(rec.normal.dot(below) > 0.0);
+        assert_eq!(material.pdf(&rec, -ray.direction, below), 0.0);
+
+        let scattered: Vec<_> = (0..1000)
+            .filter_map(|_| material.scatter(&ray, rec))
+            .collect();
+        assert!(scattered.len() > 100 && scattered.len() < 1000);
+        assert!(scattered.iter().all(|(r, _)| r.direction.z > 0.0));
     }
 }
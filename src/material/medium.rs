@@ -0,0 +1,210 @@
+use super::scatterable::{Material, Scatterable};
+use crate::{random, ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
+
+/// Homogeneous medium filling the inside of a closed surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Medium {
+    /// Fraction of light absorbed per unit distance for every color channel.
+    pub absorption: Vec3,
+    /// Fraction of light scattered into a new direction per unit distance.
+    pub scattering: Vec3,
+    pub ior: f64,
+    /// Where media overlap, the one of highest priority fills the overlap.
+    pub priority: u32,
+}
+
+impl Medium {
+    /// Absorption coefficient of a medium leaving `color` of white light after
+    /// travelling `distance` through it, which is easier to pick.
+    pub fn absorption_from_transmittance(color: Vec3, distance: f64) -> Vec3 {
+        Vec3::new(
+            -color.x.ln() / distance,
+            -color.y.ln() / distance,
+            -color.z.ln() / distance,
+        )
+    }
+
+    /// Fraction of light neither absorbed nor scattered away after travelling
+    /// `distance` through the medium, following the Beer-Lambert law.
+    pub fn transmittance(&self, distance: f64) -> Vec3 {
+        let a = (self.absorption + self.scattering) * distance;
+        Vec3::new((-a.x).exp(), (-a.y).exp(), (-a.z).exp())
+    }
+
+    /// Samples how far light travels through the medium before scattering, up
+    /// to the surface at `distance`. Gives the distance of the scattering
+    /// event, or `None` if the light reaches the surface, together with the
+    /// weight of the sample.
+    ///
+    /// The distance is sampled for one randomly chosen color channel, with the
+    /// density averaged over all channels so that colored media stay unbiased.
+    pub fn sample_distance(&self, distance: f64) -> (Option<f64>, Vec3) {
+        if self.scattering == Vec3::origin() {
+            return (None, self.transmittance(distance));
+        }
+        let extinction = self.absorption + self.scattering;
+        let channel = ((3.0 * random::uniform()) as usize).min(2);
+        let free_path = -(1.0 - random::uniform()).ln() / extinction[channel];
+        let t = free_path.min(distance);
+        let transmittance = self.transmittance(t);
+        match free_path < distance {
+            true => {
+                let density = extinction * transmittance;
+                let pdf = (density.x + density.y + density.z) / 3.0;
+                (Some(t), self.scattering * transmittance / pdf)
+            }
+            false => {
+                let pdf = (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+                (None, transmittance / pdf)
+            }
+        }
+    }
+}
+
+/// Materials whose media a path is inside, innermost last.
+///
+/// Overlapping media are resolved by their priority (Schmidt and Budge 2002):
+/// a medium is only present where no medium of higher priority is, so liquid
+/// in a glass can be modelled by letting the liquid overlap the glass wall.
+#[derive(Debug, Default)]
+pub struct MediumStack<'a> {
+    materials: Vec<&'a Material>,
+}
+
+impl<'a> MediumStack<'a> {
+    /// Medium the path is currently travelling through.
+    pub fn current(&self) -> Option<Medium> {
+        highest(self.materials.iter().copied())
+    }
+
+    /// Index of refraction of the medium inside the surface at `record`
+    /// relative to the one on the outside. `None` if the surface lies inside
+    /// a medium of higher priority and does not exist for the path.
+    pub fn relative_ior(&self, record: &HitRecord<'a>) -> Option<f64> {
+        let medium = match record.material.medium() {
+            Some(medium) => medium,
+            None => return Some(1.0),
+        };
+        // Entering or leaving, the medium on the other side is the highest of
+        // all media the path is inside apart from this one.
+        let own = self.position(record.material);
+        let others = self
+            .materials
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != own)
+            .map(|(_, m)| *m);
+        match highest(others) {
+            Some(other) if other.priority > medium.priority => None,
+            Some(other) => Some(medium.ior / other.ior),
+            None => Some(medium.ior),
+        }
+    }
+
+    /// Enters or leaves the medium of the surface at `record`, if `ray`
+    /// arriving there was transmitted through it as `scattered`.
+    pub fn update(&mut self, ray: &Ray, scattered: &Ray, record: &HitRecord<'a>) {
+        if record.material.medium().is_none() {
+            return;
+        }
+        let arriving = ray.direction.dot(record.geometric_normal);
+        let leaving = scattered.direction.dot(record.geometric_normal);
+        if arriving < 0.0 && leaving < 0.0 {
+            self.materials.push(record.material);
+        } else if arriving > 0.0 && leaving > 0.0 {
+            if let Some(i) = self.position(record.material) {
+                self.materials.remove(i);
+            }
+        }
+    }
+
+    fn position(&self, material: &Material) -> Option<usize> {
+        self.materials
+            .iter()
+            .rposition(|m| std::ptr::eq(*m, material))
+    }
+}
+
+/// Medium of highest priority among `materials`, the last one among equals.
+fn highest<'a>(materials: impl Iterator<Item = &'a Material>) -> Option<Medium> {
+    materials
+        .filter_map(|m| m.medium())
+        .fold(None, |best: Option<Medium>, m| match best {
+            Some(b) if b.priority > m.priority => Some(b),
+            _ => Some(m),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{dielectric::Dielectric, testing::record};
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn transmittance_at_distance() {
+        let color = Vec3::new(0.9, 0.5, 0.1);
+        let medium = Medium {
+            absorption: Medium::absorption_from_transmittance(color, 2.0),
+            scattering: Vec3::origin(),
+            ior: 1.0,
+            priority: 0,
+        };
+        assert_approx_eq!((medium.transmittance(2.0) - color).length(), 0.0);
+        assert_approx_eq!(medium.transmittance(4.0).y, 0.25);
+        assert_eq!(medium.transmittance(0.0), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn distance_sampling_is_unbiased() {
+        random::seed(31);
+        let medium = Medium {
+            absorption: Vec3::new(0.1, 0.5, 2.0),
+            scattering: Vec3::new(1.0, 0.5, 0.2),
+            ior: 1.0,
+            priority: 0,
+        };
+        let n = 100_000;
+        let (mut passed, mut scattered) = (Vec3::origin(), Vec3::origin());
+        for _ in 0..n {
+            match medium.sample_distance(1.5) {
+                (None, weight) => passed += weight,
+                (Some(t), weight) => {
+                    assert!(t < 1.5);
+                    scattered += weight;
+                }
+            }
+        }
+        // Light reaching the surface and light scattered once, which is the
+        // integral of the scattering coefficient along the attenuated path.
+        let extinction = medium.absorption + medium.scattering;
+        let expected = medium.transmittance(1.5);
+        let once = medium.scattering / extinction * (Vec3::new(1.0, 1.0, 1.0) - expected);
+        assert!((passed / n as f64 - expected).length() < 0.02);
+        assert!((scattered / n as f64 - once).length() < 0.02);
+    }
+
+    #[test]
+    fn water_in_glass() {
+        let glass = Material::Dielectric(Dielectric::new(1.5).with_priority(2));
+        let water = Material::Dielectric(Dielectric::new(1.33).with_priority(1));
+        let down = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let (up, dpdu) = (Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let into_glass = record(&glass, up, (0.0, 0.0), dpdu);
+        let into_water = record(&water, up, (0.0, 0.0), dpdu);
+        let out_of_glass = record(&glass, -up, (0.0, 0.0), dpdu);
+        let mut media = MediumStack::default();
+
+        // Into the glass wall, where the water overlapping it does not exist.
+        assert_approx_eq!(media.relative_ior(&into_glass).unwrap(), 1.5);
+        media.update(&down, &down, &into_glass);
+        assert_eq!(media.relative_ior(&into_water), None);
+        media.update(&down, &down, &into_water);
+        assert_eq!(media.current().unwrap().ior, 1.5);
+
+        // Out of the inner side of the wall into the water.
+        assert_approx_eq!(media.relative_ior(&out_of_glass).unwrap(), 1.5 / 1.33);
+        media.update(&down, &down, &out_of_glass);
+        assert_eq!(media.current().unwrap().ior, 1.33);
+    }
+}
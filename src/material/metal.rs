@@ -1,22 +1,225 @@
-use super::scatterable::Scatterable;
-use crate::{ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
+use super::{
+    fresnel,
+    microfacet::{ShadingFrame, TrowbridgeReitz},
+    scatterable::Scatterable,
+    thin_film::ThinFilm,
+};
+use crate::{
+    random,
+    ray::Ray,
+    shape::hittable::HitRecord,
+    texture::texture_kind::{Texture, TextureKind},
+    vec3::Vec3,
+};
 
+/// Conductor with a Trowbridge-Reitz (GGX) microfacet surface, reflecting the
+/// Fresnel reflectance of the complex index of refraction `eta + i k`.
 #[derive(Clone, Debug)]
 pub struct Metal {
-    pub albedo: Vec3,
+    /// Tint the Fresnel reflectance is multiplied by.
+    pub albedo: TextureKind,
+    pub eta: Vec3,
+    pub k: Vec3,
+    /// Perceptual roughness in `[0, 1]`, zero being a perfect mirror.
     pub roughness: f64,
+    pub thin_film: Option<ThinFilm>,
+}
+
+impl Metal {
+    /// Metal reflecting `albedo` at every angle.
+    pub fn new(albedo: TextureKind, roughness: f64) -> Metal {
+        Metal {
+            albedo,
+            eta: Vec3::origin(),
+            k: Vec3::new(1.0, 1.0, 1.0),
+            roughness,
+            thin_film: None,
+        }
+    }
+
+    /// Metal with the complex index of refraction `eta + i k` per color channel.
+    pub fn conductor(eta: Vec3, k: Vec3, roughness: f64) -> Metal {
+        Metal {
+            albedo: Vec3::new(1.0, 1.0, 1.0).into(),
+            eta,
+            k,
+            roughness,
+            thin_film: None,
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Metal {
+        Metal::conductor(
+            Vec3::new(0.143, 0.374, 1.442),
+            Vec3::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Metal {
+        Metal::conductor(
+            Vec3::new(0.200, 0.924, 1.102),
+            Vec3::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Metal {
+        Metal::conductor(
+            Vec3::new(0.155, 0.117, 0.138),
+            Vec3::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Metal {
+        Metal::conductor(
+            Vec3::new(1.657, 0.880, 0.521),
+            Vec3::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    /// Coats the surface with a thin film, such as an oxide layer.
+    pub fn with_thin_film(self, thin_film: ThinFilm) -> Metal {
+        Metal {
+            thin_film: Some(thin_film),
+            ..self
+        }
+    }
+
+    fn reflectance(&self, record: &HitRecord, cos_i: f64) -> Vec3 {
+        let fresnel = match &self.thin_film {
+            Some(film) => film.reflectance(record, cos_i, 1.0, self.eta, self.k),
+            None => fresnel::conductor_rgb(cos_i, self.eta, self.k),
+        };
+        self.albedo.value(record.uv, record.point) * fresnel
+    }
 }
 
 impl Scatterable for Metal {
     fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
-        let reflected = ray.direction.reflect(record.normal);
-        let scattered = Ray::new(
-            record.point,
-            reflected + self.roughness * Vec3::random_unit_vector(),
+        let view = -ray.direction.unitize();
+        let frame = ShadingFrame::facing(&record, view);
+        let wo = frame.to_local(view);
+        if wo.z == 0.0 {
+            return None;
+        }
+
+        let distribution = TrowbridgeReitz::new(self.roughness);
+        if distribution.is_smooth() {
+            let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+            let scattered = Ray::new(record.point, frame.from_local(wi), ray.time);
+            return Some((scattered, self.reflectance(&record, wo.z)));
+        }
+
+        let wm = distribution.sample_visible(wo, (random::uniform(), random::uniform()));
+        let wi = (-wo).reflect(wm);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        // The distribution of visible normals cancels all but the masking of
+        // the scattered direction.
+        let weight =
+            self.reflectance(&record, wo.dot(wm)) * distribution.g(wo, wi) / distribution.g1(wo);
+        let scattered = Ray::new(record.point, frame.from_local(wi), ray.time);
+        Some((scattered, weight))
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        let frame = ShadingFrame::facing(record, wo);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        let distribution = TrowbridgeReitz::new(self.roughness);
+        if distribution.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::origin();
+        }
+        let wm = (wo + wi).unitize();
+        self.reflectance(record, wo.dot(wm)) * distribution.d(wm) * distribution.g(wo, wi)
+            / (4.0 * wo.z)
+    }
+
+    fn pdf(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let frame = ShadingFrame::facing(record, wo);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        let distribution = TrowbridgeReitz::new(self.roughness);
+        if distribution.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wm = (wo + wi).unitize();
+        distribution.pdf(wo, wm) / (4.0 * wo.dot(wm))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{scatterable::Material, testing::record};
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn sampling_matches_evaluation() {
+        random::seed(7);
+        let metal = Metal::gold(0.5);
+        let material = Material::Metal(metal.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let ray = Ray::new(Vec3::new(-1.0, 0.3, 1.0), Vec3::new(1.0, -0.3, -1.0), 0.0);
+        let wo = -ray.direction.unitize();
+        for _ in 0..100 {
+            if let Some((scattered, weight)) = metal.scatter(&ray, rec) {
+                let wi = scattered.direction.unitize();
+                let expected = metal.eval(&rec, wo, wi) / metal.pdf(&rec, wo, wi);
+                assert_approx_eq!((weight - expected).length(), 0.0, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn energy_is_conserved() {
+        random::seed(8);
+        let metal = Metal::new(Vec3::new(1.0, 1.0, 1.0).into(), 0.5);
+        let material = Material::Metal(metal.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
         );
-        match Vec3::dot(scattered.direction, record.normal) > 0.0 {
-            true => Some((scattered, self.albedo)),
-            false => None,
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0), 0.0);
+        let n = 10_000;
+        let mut total = Vec3::origin();
+        for _ in 0..n {
+            if let Some((_, weight)) = metal.scatter(&ray, rec) {
+                assert!(weight.x <= 1.0 + 1e-9);
+                total += weight;
+            }
         }
+        // Single scattering loses energy to masking, increasingly so for rough
+        // surfaces, but never gains any.
+        let albedo = total.x / n as f64;
+        assert!(albedo > 0.85 && albedo <= 1.0, "albedo {}", albedo);
+    }
+
+    #[test]
+    fn smooth_metal_is_a_mirror() {
+        let metal = Metal::silver(0.0);
+        let material = Material::Metal(metal.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0), 0.0);
+        let (scattered, weight) = metal.scatter(&ray, rec).unwrap();
+        assert_approx_eq!(
+            (scattered.direction - Vec3::new(1.0, 0.0, 1.0).unitize()).length(),
+            0.0
+        );
+        assert!(weight.x > 0.9 && weight.x < 1.0);
     }
 }
@@ -0,0 +1,197 @@
+use crate::{shape::hittable::HitRecord, utility::coordinate_system, vec3::Vec3};
+use std::f64::consts::{PI, TAU};
+
+/// Orthonormal basis at a hit point with the shading normal as `z` and `x` along
+/// the direction of increasing u, in which microfacet models are evaluated.
+#[derive(Clone, Copy, Debug)]
+pub struct ShadingFrame {
+    pub x: Vec3,
+    pub y: Vec3,
+    pub z: Vec3,
+}
+
+impl ShadingFrame {
+    /// Frame of `record` with `z` along its normal.
+    pub fn new(record: &HitRecord) -> ShadingFrame {
+        ShadingFrame::around(record.normal, record.dpdu)
+    }
+
+    /// Frame of `record` with the normal flipped to the side `view` points to.
+    pub fn facing(record: &HitRecord, view: Vec3) -> ShadingFrame {
+        let z = match record.normal.dot(view) < 0.0 {
+            true => -record.normal,
+            false => record.normal,
+        };
+        ShadingFrame::around(z, record.dpdu)
+    }
+
+    fn around(z: Vec3, dpdu: Vec3) -> ShadingFrame {
+        let tangent = dpdu - z * z.dot(dpdu);
+        let (x, y) = match tangent.length_sq() > 1e-12 {
+            true => {
+                let x = tangent.unitize();
+                (x, Vec3::cross(z, x))
+            }
+            false => coordinate_system(z),
+        };
+        ShadingFrame { x, y, z }
+    }
+
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(self.x), world.dot(self.y), world.dot(self.z))
+    }
+
+    pub fn from_local(&self, local: Vec3) -> Vec3 {
+        local.x * self.x + local.y * self.y + local.z * self.z
+    }
+}
+
+/// Trowbridge-Reitz (GGX) distribution of microfacet normals around `z`, with
+/// the widths `alpha_x` and `alpha_y` along `x` and `y`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    /// Isotropic distribution for a perceptual `roughness` in `[0, 1]`, which is
+    /// squared to give the width.
+    pub fn new(roughness: f64) -> TrowbridgeReitz {
+        let alpha = roughness * roughness;
+        TrowbridgeReitz {
+            alpha_x: alpha,
+            alpha_y: alpha,
+        }
+    }
+
+    /// Distribution for a perceptual `roughness` stretched along `x` by
+    /// `anisotropic` in `[0, 1]`, kept slightly rough to remain a density
+    /// (Burley 2012).
+    pub fn anisotropic(roughness: f64, anisotropic: f64) -> TrowbridgeReitz {
+        let aspect = (1.0 - 0.9 * anisotropic).sqrt();
+        let alpha = roughness * roughness;
+        TrowbridgeReitz {
+            alpha_x: (alpha / aspect).max(1e-3),
+            alpha_y: (alpha * aspect).max(1e-3),
+        }
+    }
+
+    /// Whether the surface is smooth enough to be treated as a perfect mirror.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacet normals `wm` per projected area.
+    pub fn d(&self, wm: Vec3) -> f64 {
+        if wm.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (wm.x / self.alpha_x).powi(2) + (wm.y / self.alpha_y).powi(2) + wm.z * wm.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    /// Smith's auxiliary function, the masked microfacet area per visible area
+    /// seen from `w`.
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let alpha2_tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / cos2;
+        0.5 * ((1.0 + alpha2_tan2).sqrt() - 1.0)
+    }
+
+    /// Fraction of the microfacets visible from `w`.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of the microfacets visible from both `wo` and `wi`.
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals `wm` of the microfacets visible from `w`, with
+    /// which [`TrowbridgeReitz::sample_visible`] samples them. Directions below
+    /// the surface see the microfacets from their underside.
+    pub fn pdf(&self, w: Vec3, wm: Vec3) -> f64 {
+        let w = match w.z < 0.0 {
+            true => -w,
+            false => w,
+        };
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).max(0.0)
+    }
+
+    /// Samples the normal of a microfacet visible from `w` given two uniform
+    /// numbers `u` (Heitz 2018).
+    pub fn sample_visible(&self, w: Vec3, u: (f64, f64)) -> Vec3 {
+        let w = match w.z < 0.0 {
+            true => -w,
+            false => w,
+        };
+        // Stretch to the hemisphere configuration of a unit width.
+        let wh = Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).unitize();
+        let len_sq = wh.x * wh.x + wh.y * wh.y;
+        let t1 = match len_sq > 0.0 {
+            true => Vec3::new(-wh.y, wh.x, 0.0) / len_sq.sqrt(),
+            false => Vec3::new(1.0, 0.0, 0.0),
+        };
+        let t2 = Vec3::cross(wh, t1);
+
+        // Uniform point on the disk, warped onto the visible half of it.
+        let r = u.0.sqrt();
+        let phi = TAU * u.1;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + wh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * wh;
+
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unitize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn uniform_hemisphere() -> Vec3 {
+        let z = random::uniform();
+        let r = (1.0 - z * z).sqrt();
+        let phi = TAU * random::uniform();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    #[test]
+    fn densities_are_normalized() {
+        random::seed(3);
+        let distribution = TrowbridgeReitz {
+            alpha_x: 0.5,
+            alpha_y: 0.8,
+        };
+        let w = Vec3::new(0.5, -0.2, 0.6).unitize();
+        let n = 200_000;
+        let (mut projected, mut visible) = (0.0, 0.0);
+        for _ in 0..n {
+            let wm = uniform_hemisphere();
+            projected += distribution.d(wm) * wm.z * TAU;
+            visible += distribution.pdf(w, wm) * TAU;
+        }
+        assert_approx_eq!(projected / n as f64, 1.0, 0.03);
+        assert_approx_eq!(visible / n as f64, 1.0, 0.03);
+    }
+
+    #[test]
+    fn samples_are_visible() {
+        random::seed(4);
+        let distribution = TrowbridgeReitz::new(0.7);
+        let w = Vec3::new(0.9, 0.0, 0.1).unitize();
+        for _ in 0..1000 {
+            let wm = distribution.sample_visible(w, (random::uniform(), random::uniform()));
+            assert_approx_eq!(wm.length(), 1.0);
+            assert!(wm.z > 0.0 && w.dot(wm) >= -1e-9);
+        }
+    }
+}
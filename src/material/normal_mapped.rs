@@ -0,0 +1,275 @@
+use super::{
+    medium::Medium,
+    scatterable::{Material, Scatterable},
+};
+use crate::{
+    ray::Ray,
+    shape::hittable::HitRecord,
+    texture::texture_kind::{Texture, TextureKind},
+    utility::luminance,
+    vec3::Vec3,
+};
+
+/// Source of the detail added to the surface normal.
+#[derive(Clone, Debug)]
+pub enum NormalMap {
+    /// Tangent space normals encoded as RGB in `[0, 1]`, with x along the
+    /// direction of increasing u and z out of the surface.
+    TangentSpace(TextureKind),
+    /// Height above the surface given by the luminance of `height` times
+    /// `scale`, differentiated by finite differences of `delta` in uv.
+    Bump {
+        height: TextureKind,
+        scale: f64,
+        delta: f64,
+    },
+}
+
+/// Material shading with a normal tilted by a normal or bump map.
+#[derive(Clone, Debug)]
+pub struct NormalMapped {
+    pub material: Box<Material>,
+    pub map: NormalMap,
+}
+
+impl NormalMapped {
+    pub fn new(material: Material, map: NormalMap) -> NormalMapped {
+        NormalMapped {
+            material: Box::new(material),
+            map,
+        }
+    }
+
+    /// Shading normal at `record`, on the same side of the surface as its
+    /// normal.
+    fn normal(&self, record: &HitRecord) -> Vec3 {
+        let n = record.normal;
+        let mapped = match &self.map {
+            NormalMap::TangentSpace(texture) => {
+                let tangent = (record.dpdu - n * n.dot(record.dpdu)).unitize();
+                let mut bitangent = Vec3::cross(n, tangent);
+                if bitangent.dot(record.dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+                let c = 2.0 * texture.value(record.uv, record.point) - 1.0;
+                c.x * tangent + c.y * bitangent + c.z * n
+            }
+            NormalMap::Bump {
+                height,
+                scale,
+                delta,
+            } => {
+                let (u, v) = record.uv;
+                let height_at = |uv: (f64, f64), p: Vec3| scale * luminance(height.value(uv, p));
+                let h = height_at(record.uv, record.point);
+                let h_u = height_at((u + delta, v), record.point + *delta * record.dpdu);
+                let h_v = height_at((u, v + delta), record.point + *delta * record.dpdv);
+                let dpdu = record.dpdu + (h_u - h) / delta * n;
+                let dpdv = record.dpdv + (h_v - h) / delta * n;
+                let bumped = Vec3::cross(dpdu, dpdv);
+                match bumped.dot(n) < 0.0 {
+                    true => -bumped,
+                    false => bumped,
+                }
+            }
+        };
+        if mapped.length_sq() == 0.0 || mapped.has_nan() {
+            return n;
+        }
+        mapped.unitize()
+    }
+
+    /// Mapped normal at `record` adjusted so that the unit vector `view`
+    /// reflects to above the surface.
+    fn shading_normal(&self, record: &HitRecord, view: Vec3) -> Vec3 {
+        // Work with the normals facing the viewer.
+        let side = match record.geometric_normal.dot(view) < 0.0 {
+            true => -1.0,
+            false => 1.0,
+        };
+        let normal = self.normal(record);
+        side * ensure_valid_reflection(side * record.geometric_normal, view, side * normal)
+    }
+}
+
+impl Scatterable for NormalMapped {
+    fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
+        let normal = self.shading_normal(&record, -ray.direction.unitize());
+        self.material.scatter(ray, HitRecord { normal, ..record })
+    }
+
+    fn scatter_between(&self, ray: &Ray, record: HitRecord, eta: f64) -> Option<(Ray, Vec3)> {
+        let normal = self.shading_normal(&record, -ray.direction.unitize());
+        self.material
+            .scatter_between(ray, HitRecord { normal, ..record }, eta)
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        let normal = self.shading_normal(record, wo);
+        self.material.eval(&HitRecord { normal, ..*record }, wo, wi)
+    }
+
+    fn pdf(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let normal = self.shading_normal(record, wo);
+        self.material.pdf(&HitRecord { normal, ..*record }, wo, wi)
+    }
+
+    fn eval_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> Vec3 {
+        let normal = self.shading_normal(record, wo);
+        self.material
+            .eval_between(&HitRecord { normal, ..*record }, wo, wi, eta)
+    }
+
+    fn pdf_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> f64 {
+        let normal = self.shading_normal(record, wo);
+        self.material
+            .pdf_between(&HitRecord { normal, ..*record }, wo, wi, eta)
+    }
+
+    fn medium(&self) -> Option<Medium> {
+        self.material.medium()
+    }
+}
+
+/// Bends the shading normal `n` towards the geometric normal `ng` just enough
+/// that the view direction `view` reflects to above the surface, which would
+/// otherwise leave black areas where the map tilts the normal away from the
+/// viewer. The approach of Keller et al. (2017) as used by Cycles. Both normals
+/// and the view direction point away from the surface on the same side.
+fn ensure_valid_reflection(ng: Vec3, view: Vec3, n: Vec3) -> Vec3 {
+    let reflected = 2.0 * n.dot(view) * n - view;
+    // Reflections may always be at least as shallow as the incoming ray.
+    let threshold = f64::min(0.9 * ng.dot(view), 0.01);
+    if ng.dot(reflected) >= threshold {
+        return n;
+    }
+
+    // Work in the plane spanned by the geometric normal as z and the component
+    // of the shading normal orthogonal to it as x.
+    let x = (n - n.dot(ng) * ng).unitize();
+    let (ix, iz) = (view.dot(x), view.dot(ng));
+    let a = ix * ix + iz * iz;
+    let b = (ix * ix * (a - threshold * threshold)).max(0.0).sqrt();
+    let c = iz * threshold + a;
+    let fac = 0.5 / a;
+    let (n1_z2, n2_z2) = (fac * (b + c), fac * (c - b));
+    let valid = |z2: f64| z2 > 1e-5 && z2 <= 1.0 + 1e-5;
+    let normal = |z2: f64| ((1.0 - z2).max(0.0).sqrt(), z2.max(0.0).sqrt());
+
+    let (nx, nz) = match (valid(n1_z2), valid(n2_z2)) {
+        (true, true) => {
+            let (n1, n2) = (normal(n1_z2), normal(n2_z2));
+            let r1 = 2.0 * (n1.0 * ix + n1.1 * iz) * n1.1 - iz;
+            let r2 = 2.0 * (n2.0 * ix + n2.1 * iz) * n2.1 - iz;
+            match (r1 >= 1e-5, r2 >= 1e-5) {
+                (true, true) if r1 < r2 => n1,
+                (true, true) => n2,
+                _ if r1 > r2 => n1,
+                _ => n2,
+            }
+        }
+        (true, false) => normal(n1_z2),
+        (false, true) => normal(n2_z2),
+        (false, false) => return ng,
+    };
+    nx * x + nz * ng
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{lambertian::Lambertian, testing::record};
+    use crate::shape::hittable::Hittable;
+    use crate::shape::sphere::Sphere;
+    use crate::texture::checker::Checker;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn lambertian() -> Material {
+        Material::Lambertian(Lambertian {
+            albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+        })
+    }
+
+    #[test]
+    fn tangent_space_normals() {
+        let flat = NormalMapped::new(
+            lambertian(),
+            NormalMap::TangentSpace(Vec3::new(0.5, 0.5, 1.0).into()),
+        );
+        let material = lambertian();
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.5, 0.5),
+            Vec3::new(2.0, 0.0, 0.0),
+        );
+        let n = flat.normal(&rec);
+        assert_approx_eq!((n - Vec3::new(0.0, 0.0, 1.0)).length(), 0.0);
+
+        let tilted = NormalMapped::new(
+            lambertian(),
+            NormalMap::TangentSpace(Vec3::new(1.0, 0.5, 1.0).into()),
+        );
+        let n = tilted.normal(&rec);
+        assert_approx_eq!((n - Vec3::new(1.0, 0.0, 1.0).unitize()).length(), 0.0);
+    }
+
+    #[test]
+    fn bump_follows_slope() {
+        // The solid checker is looked up at the point, so the height rises by
+        // 0.1 where x crosses 1. The differences taken from x = 0.99 along dpdu
+        // straddle the step and lean the normal towards -x, while at x = 0.5
+        // the height is flat.
+        let step = Checker::new(Vec3::origin(), Vec3::new(0.1, 0.1, 0.1), 1.0);
+        let bumped = NormalMapped::new(
+            lambertian(),
+            NormalMap::Bump {
+                height: TextureKind::Checker(step),
+                scale: 1.0,
+                delta: 0.01,
+            },
+        );
+        let material = lambertian();
+        let mut rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.5, 0.5),
+            Vec3::new(2.0, 0.0, 0.0),
+        );
+        rec.point = Vec3::new(0.99, 0.5, 0.5);
+        let n = bumped.normal(&rec);
+        assert!(n.x < -0.1 && n.z > 0.0);
+
+        rec.point = Vec3::new(0.5, 0.5, 0.5);
+        let n = bumped.normal(&rec);
+        assert_approx_eq!((n - Vec3::new(0.0, 0.0, 1.0)).length(), 0.0);
+    }
+
+    #[test]
+    fn reflection_stays_above_surface() {
+        let ng = Vec3::new(0.0, 0.0, 1.0);
+        let view = Vec3::new(1.0, 0.0, 0.2).unitize();
+        let n = Vec3::new(-0.8, 0.0, 0.6).unitize();
+        assert!((2.0 * n.dot(view) * n - view).dot(ng) < 0.0);
+
+        let bent = ensure_valid_reflection(ng, view, n);
+        assert_approx_eq!(bent.length(), 1.0);
+        assert!((2.0 * bent.dot(view) * bent - view).dot(ng) > 0.0);
+        assert_eq!(ensure_valid_reflection(ng, view, ng), ng);
+    }
+
+    #[test]
+    fn scatter_from_mapped_sphere() {
+        let sphere = Sphere::new(
+            Vec3::origin(),
+            1.0,
+            Material::NormalMapped(NormalMapped::new(
+                lambertian(),
+                NormalMap::TangentSpace(Vec3::new(0.9, 0.5, 0.6).into()),
+            )),
+        );
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = sphere.hit(&ray, 0.0..f64::INFINITY).unwrap();
+        assert!(rec.material.scatter(&ray, rec).is_some());
+    }
+}
@@ -0,0 +1,434 @@
+use super::{
+    dielectric::Dielectric,
+    fresnel::schlick_weight,
+    microfacet::{ShadingFrame, TrowbridgeReitz},
+    scatterable::Scatterable,
+};
+use crate::{
+    random,
+    ray::Ray,
+    shape::hittable::HitRecord,
+    texture::texture_kind::{Texture, TextureKind},
+    utility::{lerp, luminance},
+    vec3::Vec3,
+};
+use std::f64::consts::{PI, TAU};
+
+/// Disney principled BSDF (Burley 2012, 2015), layering a clearcoat over a
+/// blend of diffuse, metallic and glass-like surfaces. The parameters follow
+/// the metallic-roughness model of glTF and most authoring tools, and are
+/// meant to be in `[0, 1]` except for `ior`.
+#[derive(Clone, Debug)]
+pub struct Principled {
+    pub base_color: TextureKind,
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Reflectance of dielectrics at normal incidence, scaled so that `0.5`
+    /// is the 4% of common materials.
+    pub specular: f64,
+    /// Amount the dielectric reflection is tinted towards the base color.
+    pub specular_tint: f64,
+    /// Extra retroreflection at grazing angles, for cloth.
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// Strength of a second, colorless specular layer on top.
+    pub clearcoat: f64,
+    /// Smoothness of the clearcoat, one being glossy and zero satin.
+    pub clearcoat_gloss: f64,
+    /// Amount of light refracting into the surface rather than scattering
+    /// diffusely, tinted by the base color.
+    pub transmission: f64,
+    /// Index of refraction of the transmissive part.
+    pub ior: f64,
+    /// Stretch of the specular highlight along the direction of increasing u.
+    pub anisotropic: f64,
+}
+
+impl Principled {
+    /// Plastic-like dielectric with the default parameters of the model.
+    pub fn new(base_color: TextureKind) -> Principled {
+        Principled {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+            anisotropic: 0.0,
+        }
+    }
+
+    /// Maps a Wavefront MTL material onto the model. The specular exponent is
+    /// converted to a roughness of matching highlight width, and dissolved
+    /// materials become transmissive.
+    pub fn from_mtl(material: &obj::Material) -> Principled {
+        let color = |c: [f32; 3]| Vec3::new(c[0] as f64, c[1] as f64, c[2] as f64);
+        let mut principled = Principled::new(color(material.kd.unwrap_or([0.8, 0.8, 0.8])).into());
+        if let Some(ns) = material.ns {
+            // Width of a Phong lobe in terms of the distribution's alpha.
+            let alpha = (2.0 / (ns as f64 + 2.0)).sqrt();
+            principled.roughness = alpha.sqrt();
+        }
+        if let Some(ks) = material.ks {
+            principled.specular = (luminance(color(ks)) / 0.08).min(1.0);
+        }
+        if let Some(ni) = material.ni {
+            principled.ior = ni as f64;
+        }
+        if let Some(d) = material.d.or(material.tr.map(|tr| 1.0 - tr)) {
+            principled.transmission = 1.0 - d as f64;
+        }
+        principled
+    }
+
+    fn lobes(&self, record: &HitRecord) -> Lobes {
+        let base = self.base_color.value(record.uv, record.point);
+        let tint = match luminance(base) > 0.0 {
+            true => base / luminance(base),
+            false => Vec3::new(1.0, 1.0, 1.0),
+        };
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let dielectric_specular = self.specular * 0.08 * lerp(white, tint, self.specular_tint);
+        let specular_color = lerp(dielectric_specular, base, self.metallic);
+
+        let glass_weight = (1.0 - self.metallic) * self.transmission;
+        let diffuse_weight = (1.0 - self.metallic) * (1.0 - self.transmission);
+        let specular_weight = 1.0 - glass_weight;
+        let clearcoat_weight = 0.25 * self.clearcoat;
+        let probabilities = [
+            diffuse_weight * luminance(base),
+            specular_weight * (0.25 + 0.75 * luminance(specular_color)),
+            clearcoat_weight,
+            glass_weight,
+        ];
+        let total: f64 = probabilities.iter().sum();
+
+        Lobes {
+            base,
+            roughness: self.roughness,
+            specular_color,
+            sheen_color: self.sheen * lerp(white, tint, self.sheen_tint),
+            diffuse_weight,
+            specular_weight,
+            clearcoat_weight,
+            glass_weight,
+            distribution: TrowbridgeReitz::anisotropic(self.roughness, self.anisotropic),
+            clearcoat_alpha: lerp(0.1, 0.001, self.clearcoat_gloss),
+            glass: Dielectric::new(self.ior).with_roughness(self.roughness),
+            probabilities: probabilities.map(|p| match total > 0.0 {
+                true => p / total,
+                false => 0.0,
+            }),
+        }
+    }
+}
+
+/// Parameters of a [`Principled`] surface at one point.
+struct Lobes {
+    base: Vec3,
+    roughness: f64,
+    specular_color: Vec3,
+    sheen_color: Vec3,
+    diffuse_weight: f64,
+    specular_weight: f64,
+    clearcoat_weight: f64,
+    glass_weight: f64,
+    distribution: TrowbridgeReitz,
+    clearcoat_alpha: f64,
+    glass: Dielectric,
+    /// Probabilities of sampling the diffuse, specular, clearcoat and glass
+    /// lobes.
+    probabilities: [f64; 4],
+}
+
+impl Lobes {
+    /// Lobes reflecting off the outside, for local directions on the side of
+    /// the normal.
+    fn eval_opaque(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::origin();
+        }
+        let h = (wo + wi).unitize();
+        let cos_d = wi.dot(h);
+        let (fi, fo, fd) = (
+            schlick_weight(wi.z),
+            schlick_weight(wo.z),
+            schlick_weight(cos_d),
+        );
+
+        // Diffuse with retroreflection at grazing angles for rough surfaces.
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let diffuse = self.base / PI * lerp(1.0, fd90, fi) * lerp(1.0, fd90, fo);
+        let sheen = fd * self.sheen_color;
+
+        let fresnel = lerp(self.specular_color, Vec3::new(1.0, 1.0, 1.0), fd);
+        let specular =
+            fresnel * self.distribution.d(h) * self.distribution.g(wo, wi) / (4.0 * wo.z * wi.z);
+
+        let coat = TrowbridgeReitz {
+            alpha_x: 0.25,
+            alpha_y: 0.25,
+        };
+        let clearcoat =
+            lerp(0.04, 1.0, fd) * gtr1(h.z, self.clearcoat_alpha) * coat.g1(wo) * coat.g1(wi)
+                / (4.0 * wo.z * wi.z);
+
+        ((diffuse + sheen) * self.diffuse_weight
+            + specular * self.specular_weight
+            + Vec3::new(1.0, 1.0, 1.0) * clearcoat * self.clearcoat_weight)
+            * wi.z
+    }
+
+    fn pdf_opaque(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).unitize();
+        let [diffuse, specular, clearcoat, _] = self.probabilities;
+        diffuse * wi.z / PI
+            + specular * self.distribution.pdf(wo, h) / (4.0 * wo.dot(h))
+            + clearcoat * gtr1(h.z, self.clearcoat_alpha) * h.z / (4.0 * wo.dot(h))
+    }
+
+    /// Refracting lobe, for local directions relative to the outward normal.
+    fn eval_glass(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> (Vec3, f64) {
+        let (f, pdf) = self
+            .glass
+            .evaluate(record, wo, wi, self.glass.index_of_refraction);
+        let tint = match wo.z * wi.z < 0.0 {
+            true => self.base,
+            false => Vec3::new(1.0, 1.0, 1.0),
+        };
+        (tint * f * self.glass_weight, pdf * self.probabilities[3])
+    }
+}
+
+/// Generalized Trowbridge-Reitz distribution with an exponent of one, whose
+/// long tails give the clearcoat its haze.
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    if alpha >= 1.0 {
+        return 1.0 / PI;
+    }
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+impl Scatterable for Principled {
+    fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
+        let view = -ray.direction.unitize();
+        let lobes = self.lobes(&record);
+        let frame = ShadingFrame::facing(&record, view);
+        let wo = frame.to_local(view);
+        let [diffuse, specular, clearcoat, _] = lobes.probabilities;
+
+        let u = random::uniform();
+        let wi = if u < diffuse {
+            frame.z + Vec3::random_unit_vector()
+        } else if u < diffuse + specular {
+            let wm = lobes
+                .distribution
+                .sample_visible(wo, (random::uniform(), random::uniform()));
+            frame.from_local((-wo).reflect(wm))
+        } else if u < diffuse + specular + clearcoat {
+            let a2 = lobes.clearcoat_alpha * lobes.clearcoat_alpha;
+            let cos2 = (1.0 - a2.powf(1.0 - random::uniform())) / (1.0 - a2);
+            let sin = (1.0 - cos2).max(0.0).sqrt();
+            let phi = TAU * random::uniform();
+            let h = Vec3::new(sin * phi.cos(), sin * phi.sin(), cos2.sqrt());
+            frame.from_local((-wo).reflect(h))
+        } else {
+            let (scattered, weight) = lobes.glass.scatter(ray, record)?;
+            if TrowbridgeReitz::new(lobes.roughness).is_smooth() {
+                // Smooth glass scatters into a single direction, which none of
+                // the other lobes can produce.
+                let transmitted = scattered.direction.dot(record.normal) * view.dot(record.normal);
+                let tint = match transmitted < 0.0 {
+                    true => lobes.base,
+                    false => Vec3::new(1.0, 1.0, 1.0),
+                };
+                let p = lobes.probabilities[3];
+                return Some((scattered, weight * tint * lobes.glass_weight / p));
+            }
+            scattered.direction
+        };
+
+        // The reflecting lobes have no density below the surface, so their
+        // samples there must not be weighted by the glass lobe's pdf.
+        let reflecting = u < diffuse + specular + clearcoat;
+        if reflecting && frame.to_local(wi).z <= 0.0 {
+            return None;
+        }
+
+        let wi = wi.unitize();
+        let pdf = self.pdf(&record, view, wi);
+        if wi.has_nan() || pdf <= 0.0 {
+            return None;
+        }
+        let scattered = Ray::new(record.point, wi, ray.time);
+        Some((scattered, self.eval(&record, view, wi) / pdf))
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        let lobes = self.lobes(record);
+        let facing = ShadingFrame::facing(record, wo);
+        let outward = ShadingFrame::new(record);
+        let opaque = lobes.eval_opaque(facing.to_local(wo), facing.to_local(wi));
+        let (glass, _) = lobes.eval_glass(record, outward.to_local(wo), outward.to_local(wi));
+        opaque + glass
+    }
+
+    fn pdf(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let lobes = self.lobes(record);
+        let facing = ShadingFrame::facing(record, wo);
+        let outward = ShadingFrame::new(record);
+        let opaque = lobes.pdf_opaque(facing.to_local(wo), facing.to_local(wi));
+        let (_, glass) = lobes.eval_glass(record, outward.to_local(wo), outward.to_local(wi));
+        opaque + glass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{scatterable::Material, testing::record};
+    use assert_approx_eq::assert_approx_eq;
+
+    /// Compares how often sampled directions land in bins of equal solid angle
+    /// with the pdf integrated over each bin.
+    #[test]
+    fn sampling_follows_pdf() {
+        random::seed(21);
+        let principled = Principled {
+            metallic: 0.3,
+            roughness: 0.5,
+            sheen: 0.5,
+            clearcoat: 0.8,
+            clearcoat_gloss: 0.2,
+            transmission: 0.4,
+            anisotropic: 0.4,
+            ..Principled::new(Vec3::new(0.8, 0.3, 0.2).into())
+        };
+        let material = Material::Principled(principled.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let (z_bins, phi_bins) = (10, 12);
+        let bin = |wi: Vec3| {
+            let z = (((wi.z + 1.0) / 2.0 * z_bins as f64) as usize).min(z_bins - 1);
+            let phi = wi.y.atan2(wi.x).rem_euclid(TAU);
+            let phi = ((phi / TAU * phi_bins as f64) as usize).min(phi_bins - 1);
+            z * phi_bins + phi
+        };
+
+        for direction in [Vec3::new(0.6, 0.2, -1.0), Vec3::new(0.3, -0.2, 1.0)] {
+            let ray = Ray::new(-direction, direction, 0.0);
+            let wo = -direction.unitize();
+
+            let n = 200_000;
+            let mut histogram = vec![0.0; z_bins * phi_bins];
+            for _ in 0..n {
+                if let Some((scattered, _)) = principled.scatter(&ray, rec) {
+                    histogram[bin(scattered.direction)] += 1.0 / n as f64;
+                }
+            }
+
+            // Midpoint rule over a grid in z and phi, where the solid angle is
+            // dz dphi.
+            let steps = 16;
+            let (dz, dphi) = (
+                2.0 / (z_bins * steps) as f64,
+                TAU / (phi_bins * steps) as f64,
+            );
+            let mut expected = vec![0.0; z_bins * phi_bins];
+            for i in 0..z_bins * steps {
+                for j in 0..phi_bins * steps {
+                    let z = -1.0 + (i as f64 + 0.5) * dz;
+                    let phi = (j as f64 + 0.5) * dphi;
+                    let r = (1.0 - z * z).sqrt();
+                    let wi = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                    expected[bin(wi)] += principled.pdf(&rec, wo, wi) * dz * dphi;
+                }
+            }
+
+            for (sampled, integrated) in histogram.iter().zip(&expected) {
+                assert_approx_eq!(sampled, integrated, 3e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn white_metal_conserves_energy() {
+        random::seed(22);
+        let principled = Principled {
+            metallic: 1.0,
+            ..Principled::new(Vec3::new(1.0, 1.0, 1.0).into())
+        };
+        let material = Material::Principled(principled.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let direction = Vec3::new(1.0, 0.0, -1.0);
+        let ray = Ray::new(-direction, direction, 0.0);
+        let n = 10_000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            if let Some((_, weight)) = principled.scatter(&ray, rec) {
+                total += weight.x;
+            }
+        }
+        let albedo = total / n as f64;
+        assert!(albedo > 0.8 && albedo <= 1.0, "albedo {}", albedo);
+    }
+
+    #[test]
+    fn smooth_transmission_is_tinted_glass() {
+        random::seed(23);
+        let principled = Principled {
+            roughness: 0.0,
+            transmission: 1.0,
+            ..Principled::new(Vec3::new(0.5, 1.0, 1.0).into())
+        };
+        let material = Material::Principled(principled.clone());
+        let rec = record(
+            &material,
+            Vec3::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        let ray = Ray::new(-direction, direction, 0.0);
+        for _ in 0..100 {
+            let (scattered, weight) = principled.scatter(&ray, rec).unwrap();
+            let d = scattered.direction.unitize();
+            if d.z < 0.0 {
+                assert_approx_eq!((d - direction).length(), 0.0);
+                assert_approx_eq!(weight.x, 0.5 / 2.25);
+                assert_approx_eq!(weight.y, 1.0 / 2.25);
+            }
+        }
+    }
+
+    #[test]
+    fn mtl_mapping() {
+        let mut mtl = obj::Material::new("glass".to_string());
+        mtl.kd = Some([0.2, 0.4, 0.6]);
+        mtl.ns = Some(98.0);
+        mtl.ni = Some(1.33);
+        mtl.d = Some(0.25);
+        let principled = Principled::from_mtl(&mtl);
+        assert_approx_eq!(principled.roughness, 0.02f64.powf(0.25));
+        assert_approx_eq!(principled.ior, 1.33, 1e-6);
+        assert_approx_eq!(principled.transmission, 0.75);
+    }
+}
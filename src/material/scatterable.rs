@@ -1,10 +1,50 @@
-use super::{dielectric::Dielectric, lambertian::Lambertian, metal::Metal};
+use super::{
+    dielectric::Dielectric, lambertian::Lambertian, medium::Medium, metal::Metal,
+    normal_mapped::NormalMapped, principled::Principled, subsurface::Subsurface,
+};
 use crate::{ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
 use enum_dispatch::enum_dispatch;
 
 #[enum_dispatch(Material)]
 pub trait Scatterable: std::fmt::Debug {
     fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)>;
+
+    /// Like [`Scatterable::scatter`] for a surface whose [`Scatterable::medium`]
+    /// borders another medium, with `eta` the index of refraction of the inside
+    /// relative to the outside.
+    fn scatter_between(&self, ray: &Ray, record: HitRecord, _eta: f64) -> Option<(Ray, Vec3)> {
+        self.scatter(ray, record)
+    }
+
+    /// BSDF times the cosine of `wi` to the shading normal, for light arriving
+    /// from `wi` and leaving towards `wo`, both unit vectors pointing away from
+    /// the surface. Zero for materials that only scatter into discrete
+    /// directions.
+    fn eval(&self, _record: &HitRecord, _wo: Vec3, _wi: Vec3) -> Vec3 {
+        Vec3::origin()
+    }
+
+    /// Solid angle density with which [`Scatterable::scatter`] samples `wi`
+    /// for a ray arriving from `wo`.
+    fn pdf(&self, _record: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    /// [`Scatterable::eval`] matching [`Scatterable::scatter_between`].
+    fn eval_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, _eta: f64) -> Vec3 {
+        self.eval(record, wo, wi)
+    }
+
+    /// [`Scatterable::pdf`] matching [`Scatterable::scatter_between`].
+    fn pdf_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, _eta: f64) -> f64 {
+        self.pdf(record, wo, wi)
+    }
+
+    /// Medium filling the inside of surfaces made of the material, which
+    /// paths refracting through them travel in.
+    fn medium(&self) -> Option<Medium> {
+        None
+    }
 }
 
 #[enum_dispatch]
@@ -13,4 +53,7 @@ pub enum Material {
     Lambertian,
     Dielectric,
     Metal,
+    NormalMapped,
+    Principled,
+    Subsurface,
 }
@@ -0,0 +1,83 @@
+use super::{dielectric::Dielectric, medium::Medium, scatterable::Scatterable};
+use crate::{ray::Ray, shape::hittable::HitRecord, vec3::Vec3};
+
+/// Translucent material such as skin, wax or marble. Light refracting into the
+/// closed surface takes a random walk through the scattering medium inside
+/// until it leaves again, possibly far from where it entered.
+#[derive(Clone, Debug)]
+pub struct Subsurface {
+    /// Fraction of light scattered rather than absorbed at every scattering
+    /// event, per color channel.
+    pub albedo: Vec3,
+    /// Mean distance light travels between scattering events, per color
+    /// channel.
+    pub mean_free_path: Vec3,
+    /// Dielectric interface of the surface.
+    pub boundary: Dielectric,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Vec3, mean_free_path: Vec3, ior: f64) -> Subsurface {
+        Subsurface {
+            albedo,
+            mean_free_path,
+            boundary: Dielectric::new(ior),
+        }
+    }
+}
+
+impl Scatterable for Subsurface {
+    fn scatter(&self, ray: &Ray, record: HitRecord) -> Option<(Ray, Vec3)> {
+        self.boundary.scatter(ray, record)
+    }
+
+    fn scatter_between(&self, ray: &Ray, record: HitRecord, eta: f64) -> Option<(Ray, Vec3)> {
+        self.boundary.scatter_between(ray, record, eta)
+    }
+
+    fn eval(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> Vec3 {
+        self.boundary.eval(record, wo, wi)
+    }
+
+    fn pdf(&self, record: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        self.boundary.pdf(record, wo, wi)
+    }
+
+    fn eval_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> Vec3 {
+        self.boundary.eval_between(record, wo, wi, eta)
+    }
+
+    fn pdf_between(&self, record: &HitRecord, wo: Vec3, wi: Vec3, eta: f64) -> f64 {
+        self.boundary.pdf_between(record, wo, wi, eta)
+    }
+
+    fn medium(&self) -> Option<Medium> {
+        let extinction = Vec3::new(1.0, 1.0, 1.0) / self.mean_free_path;
+        let scattering = self.albedo * extinction;
+        Some(Medium {
+            absorption: extinction - scattering,
+            scattering,
+            ior: self.boundary.index_of_refraction,
+            priority: self.boundary.priority,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn medium_from_albedo_and_mean_free_path() {
+        let skin = Subsurface::new(Vec3::new(0.9, 0.6, 0.0), Vec3::new(0.5, 0.25, 2.0), 1.4);
+        let medium = skin.medium().unwrap();
+        let extinction = medium.absorption + medium.scattering;
+        let expected = [(0.9, 2.0), (0.6, 4.0), (0.0, 0.5)];
+        for (channel, (albedo, coefficient)) in expected.into_iter().enumerate() {
+            assert_approx_eq!(extinction[channel], coefficient);
+            assert_approx_eq!(medium.scattering[channel] / extinction[channel], albedo);
+        }
+        assert_eq!(medium.ior, 1.4);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    shape::hittable::HitRecord,
+    texture::texture_kind::{Texture, TextureKind},
+    utility::luminance,
+    vec3::Vec3,
+};
+use std::{
+    f64::consts::TAU,
+    ops::{Add, Div, Mul, Sub},
+};
+
+/// Wavelengths in nanometers standing in for the red, green and blue channels.
+const WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+/// Thin transparent coating on a surface. Light reflected off its top and
+/// bottom interferes, coloring soap bubbles, oil slicks and coated lenses.
+#[derive(Clone, Debug)]
+pub struct ThinFilm {
+    /// Thickness in nanometers, given by the luminance of the texture so that
+    /// it may vary over the surface.
+    pub thickness: TextureKind,
+    pub ior: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: TextureKind, ior: f64) -> ThinFilm {
+        ThinFilm { thickness, ior }
+    }
+
+    /// Reflectance of the coated surface at `record` for every color channel,
+    /// for light arriving at an angle with cosine `cos_i` from the medium of
+    /// index `outside` onto a substrate of complex index `eta + i k`.
+    pub fn reflectance(
+        &self,
+        record: &HitRecord,
+        cos_i: f64,
+        outside: f64,
+        eta: Vec3,
+        k: Vec3,
+    ) -> Vec3 {
+        let thickness = luminance(self.thickness.value(record.uv, record.point)).max(0.0);
+        let channel = |i: usize| {
+            let substrate = Complex::new(eta[i], k[i]);
+            airy(
+                cos_i,
+                outside,
+                self.ior,
+                substrate,
+                thickness,
+                WAVELENGTHS[i],
+            )
+        };
+        Vec3::new(channel(0), channel(1), channel(2))
+    }
+}
+
+/// Reflectance of a film of index `n1` and `thickness` between the medium of
+/// index `n0` light arrives from and a substrate of index `n2`, for light of
+/// `wavelength` at an angle with cosine `cos0` to the normal. Sums the
+/// amplitudes of all reflections inside the film and averages over both
+/// polarizations.
+fn airy(cos0: f64, n0: f64, n1: f64, n2: Complex, thickness: f64, wavelength: f64) -> f64 {
+    let cos0 = cos0.clamp(0.0, 1.0);
+    let sin2 = Complex::real(n0 * n0 * (1.0 - cos0 * cos0));
+    let (n0, n1) = (Complex::real(n0), Complex::real(n1));
+    let one = Complex::real(1.0);
+    let cos0 = Complex::real(cos0);
+    // Snell's law, with complex cosines past the critical angle and inside
+    // absorbing substrates.
+    let cos1 = (one - sin2 / (n1 * n1)).sqrt();
+    let cos2 = (one - sin2 / (n2 * n2)).sqrt();
+
+    let rs01 = (n0 * cos0 - n1 * cos1) / (n0 * cos0 + n1 * cos1);
+    let rp01 = (n1 * cos0 - n0 * cos1) / (n1 * cos0 + n0 * cos1);
+    let rs12 = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let rp12 = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+
+    // Phase difference of one round trip through the film.
+    let phase = (Complex::real(2.0 * TAU * thickness / wavelength) * n1 * cos1).exp_i();
+    let total =
+        |r01: Complex, r12: Complex| ((r01 + r12 * phase) / (one + r01 * r12 * phase)).norm_sqr();
+    (0.5 * (total(rs01, rs12) + total(rp01, rp12))).clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn real(re: f64) -> Complex {
+        Complex { re, im: 0.0 }
+    }
+
+    fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Principal square root, with a non-negative real part.
+    fn sqrt(self) -> Complex {
+        let r = self.norm_sqr().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+        Complex::new(re, im.copysign(self.im))
+    }
+
+    /// `e^(i z)` of this number `z`.
+    fn exp_i(self) -> Complex {
+        let scale = (-self.im).exp();
+        Complex::new(scale * self.re.cos(), scale * self.re.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let d = other.norm_sqr();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::fresnel;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn vanishing_film_leaves_fresnel() {
+        for cos in [1.0, 0.7, 0.2] {
+            let glass = airy(cos, 1.0, 1.33, Complex::real(1.5), 0.0, 500.0);
+            assert_approx_eq!(glass, fresnel::dielectric(cos, 1.5));
+            let gold = airy(cos, 1.0, 1.33, Complex::new(0.143, 3.983), 0.0, 500.0);
+            assert_approx_eq!(gold, fresnel::conductor(cos, 0.143, 3.983));
+        }
+        // Inside glass past the critical angle.
+        assert_approx_eq!(airy(0.5, 1.5, 1.33, Complex::real(1.0), 0.0, 500.0), 1.0);
+    }
+
+    #[test]
+    fn quarter_wave_coating() {
+        // The two reflections cancel for the wavelength the coating is made for.
+        let n1 = 1.5f64.sqrt();
+        let thickness = 532.0 / (4.0 * n1);
+        assert_approx_eq!(
+            airy(1.0, 1.0, n1, Complex::real(1.5), thickness, 532.0),
+            0.0
+        );
+        assert!(airy(1.0, 1.0, n1, Complex::real(1.5), thickness, 400.0) > 0.001);
+        // A half wave film has no effect.
+        let reflectance = airy(1.0, 1.0, n1, Complex::real(1.5), 2.0 * thickness, 532.0);
+        assert_approx_eq!(reflectance, 0.04);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    camera::projection::{Camera, Projection},
+    random::{self, with_primary_sampler, PrimarySampler},
+    render::{color, Film},
+    shape::hittable::Hittable,
+    utility::luminance,
+    vec3::Vec3,
+};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use rayon::prelude::*;
+use std::f64::consts::TAU;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct PrimarySample {
+    value: f64,
+    last_modification: u64,
+    value_backup: f64,
+    modification_backup: u64,
+}
+
+impl PrimarySample {
+    fn backup(&mut self) {
+        self.value_backup = self.value;
+        self.modification_backup = self.last_modification;
+    }
+
+    fn restore(&mut self) {
+        self.value = self.value_backup;
+        self.last_modification = self.modification_backup;
+    }
+}
+
+/// Primary sample space sampler for Metropolis light transport.
+///
+/// The random numbers drawn while tracing a path form a vector in the unit
+/// hypercube. Each iteration either replaces the whole vector (a large step) or
+/// perturbs every component by a small Gaussian offset, wrapping around at the
+/// unit interval. Components are mutated lazily when they are first drawn in an
+/// iteration, so paths of any length can be mutated.
+#[derive(Clone, Debug)]
+pub struct MltSampler {
+    rng: Pcg64Mcg,
+    sigma: f64,
+    large_step_probability: f64,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    index: usize,
+}
+
+impl MltSampler {
+    pub fn new(seed: u64, sigma: f64, large_step_probability: f64) -> MltSampler {
+        MltSampler {
+            rng: Pcg64Mcg::seed_from_u64(seed),
+            sigma,
+            large_step_probability,
+            samples: vec![],
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            index: 0,
+        }
+    }
+
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+        self.index = 0;
+    }
+
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    pub fn reject(&mut self) {
+        for sample in self.samples.iter_mut() {
+            if sample.last_modification == self.iteration {
+                sample.restore();
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    /// Next component of the primary sample vector.
+    pub fn next_sample(&mut self) -> f64 {
+        let index = self.index;
+        self.index += 1;
+        if index >= self.samples.len() {
+            self.samples.resize(index + 1, PrimarySample::default());
+        }
+
+        let sample = &mut self.samples[index];
+        // Components not drawn since the last accepted large step are stale and
+        // are first brought up to date with a fresh uniform value.
+        if sample.last_modification < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.last_modification = self.last_large_step;
+        }
+        sample.backup();
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            let small_steps = (self.iteration - sample.last_modification) as f64;
+            let normal = (-2.0 * (1.0 - self.rng.gen::<f64>()).ln()).sqrt()
+                * (TAU * self.rng.gen::<f64>()).cos();
+            sample.value += normal * self.sigma * small_steps.sqrt();
+            sample.value -= sample.value.floor();
+        }
+        sample.last_modification = self.iteration;
+        sample.value
+    }
+}
+
+impl PrimarySampler for MltSampler {
+    fn next_sample(&mut self) -> f64 {
+        MltSampler::next_sample(self)
+    }
+}
+
+impl Default for MltSampler {
+    fn default() -> Self {
+        MltSampler::new(0, 0.01, 0.3)
+    }
+}
+
+/// Primary sample space Metropolis light transport (Kelemen et al. 2002) on top
+/// of the path tracer in [`color`].
+///
+/// Image brightness is estimated from `bootstrap_samples` independent paths,
+/// which also seed the starting states of the Markov chains.
+#[derive(Clone, Copy, Debug)]
+pub struct Mlt {
+    pub bootstrap_samples: usize,
+    pub chains: usize,
+    pub mutations_per_pixel: u32,
+    pub sigma: f64,
+    pub large_step_probability: f64,
+}
+
+impl Default for Mlt {
+    fn default() -> Self {
+        Mlt {
+            bootstrap_samples: 100_000,
+            chains: 1000,
+            mutations_per_pixel: 100,
+            sigma: 0.01,
+            large_step_probability: 0.3,
+        }
+    }
+}
+
+impl Mlt {
+    /// Traces the path given by the primary sample vector of `sampler`, returning
+    /// the pixel it passes through and the radiance it carries.
+    fn path<T: Hittable>(
+        sampler: &mut MltSampler,
+        resolution: (u32, u32),
+        scene: &T,
+        camera: &Camera,
+    ) -> (usize, Vec3) {
+        with_primary_sampler(sampler, || {
+            let (width, height) = resolution;
+            let u = random::uniform();
+            let v = random::uniform();
+            let x = ((u * width as f64) as u32).min(width - 1);
+            let y = ((v * height as f64) as u32).min(height - 1);
+            let pixel = ((height - 1 - y) * width + x) as usize;
+            let radiance = match camera.get_weighted_ray((u, v)) {
+                Some((ray, weight)) => weight * color(&ray, scene, 0),
+                None => Vec3::origin(),
+            };
+            (pixel, radiance)
+        })
+    }
+
+    pub fn render<T: Hittable + Sync>(
+        &self,
+        resolution: (u32, u32),
+        scene: &T,
+        camera: &Camera,
+        seed: u64,
+    ) -> Film {
+        let sampler = |index: u64| {
+            MltSampler::new(
+                random::sample_seed(seed, index, 0),
+                self.sigma,
+                self.large_step_probability,
+            )
+        };
+
+        let weights: Vec<f64> = (0..self.bootstrap_samples as u64)
+            .into_par_iter()
+            .map(|i| luminance(Mlt::path(&mut sampler(i), resolution, scene, camera).1))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let mut film = Film::new(resolution);
+        if total <= 0.0 {
+            return film;
+        }
+        let brightness = total / self.bootstrap_samples as f64;
+        let cdf: Vec<f64> = weights
+            .iter()
+            .scan(0.0, |acc, w| {
+                *acc += w / total;
+                Some(*acc)
+            })
+            .collect();
+
+        let n_pixels = (resolution.0 * resolution.1) as u64;
+        let total_mutations = self.mutations_per_pixel as u64 * n_pixels;
+        let chains = self.chains as u64;
+        let splats = (0..chains)
+            .into_par_iter()
+            .fold(
+                || vec![Vec3::origin(); n_pixels as usize],
+                |mut splats, chain| {
+                    let mutations =
+                        total_mutations / chains + (chain < total_mutations % chains) as u64;
+                    let mut rng = Pcg64Mcg::seed_from_u64(random::sample_seed(seed, chain, 1));
+                    let pick = rng.gen::<f64>();
+                    let start = cdf.partition_point(|c| *c < pick).min(cdf.len() - 1);
+
+                    let mut sampler = sampler(start as u64);
+                    let (mut pixel, mut radiance) =
+                        Mlt::path(&mut sampler, resolution, scene, camera);
+                    for _ in 0..mutations {
+                        sampler.start_iteration();
+                        let (proposed_pixel, proposed) =
+                            Mlt::path(&mut sampler, resolution, scene, camera);
+                        let current_lum = luminance(radiance);
+                        let proposed_lum = luminance(proposed);
+                        let accept = match current_lum > 0.0 {
+                            true => f64::min(1.0, proposed_lum / current_lum),
+                            false => 1.0,
+                        };
+                        if accept > 0.0 {
+                            splats[proposed_pixel] += proposed * accept / proposed_lum;
+                        }
+                        if current_lum > 0.0 {
+                            splats[pixel] += radiance * (1.0 - accept) / current_lum;
+                        }
+
+                        if rng.gen::<f64>() < accept {
+                            pixel = proposed_pixel;
+                            radiance = proposed;
+                            sampler.accept();
+                        } else {
+                            sampler.reject();
+                        }
+                    }
+                    splats
+                },
+            )
+            .reduce(
+                || vec![Vec3::origin(); n_pixels as usize],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                    a
+                },
+            );
+
+        let scale = brightness / self.mutations_per_pixel as f64;
+        for (pixel, splat) in film.pixels.iter_mut().zip(splats) {
+            *pixel = splat * scale;
+        }
+        film.samples.fill(1);
+        film
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_scene::create_5_sphere_scene;
+    use crate::render::render_pass;
+
+    #[test]
+    fn rejected_mutation_restores_samples() {
+        let mut sampler = MltSampler::new(3, 0.01, 0.0);
+        let initial: Vec<f64> = (0..4).map(|_| sampler.next_sample()).collect();
+        sampler.accept();
+
+        sampler.start_iteration();
+        let mutated: Vec<f64> = (0..4).map(|_| sampler.next_sample()).collect();
+        assert_ne!(initial, mutated);
+        assert!(initial
+            .iter()
+            .zip(&mutated)
+            .all(|(a, b)| (a - b).abs() < 0.2 || (a - b).abs() > 0.8));
+        sampler.reject();
+
+        let restored: Vec<f64> = sampler.samples.iter().map(|s| s.value).collect();
+        assert_eq!(initial, restored);
+    }
+
+    #[test]
+    fn brightness_matches_path_tracing() {
+        let resolution = (16, 8);
+        let (camera, world) = create_5_sphere_scene(16.0, 8.0);
+        let scene = world.as_slice();
+        let mlt = Mlt {
+            bootstrap_samples: 4000,
+            chains: 16,
+            mutations_per_pixel: 32,
+            ..Mlt::default()
+        };
+        let mlt_film = mlt.render(resolution, &scene, &camera, 1);
+
+        let mut pt_film = Film::new(resolution);
+        for sample in 0..32 {
+            render_pass(&mut pt_film, &scene, &camera, 1, sample);
+        }
+
+        let mean = |film: &Film| {
+            (0..film.pixels.len())
+                .map(|i| luminance(film.mean(i)))
+                .sum::<f64>()
+                / film.pixels.len() as f64
+        };
+        let (a, b) = (mean(&mlt_film), mean(&pt_film));
+        assert!((a - b).abs() / b < 0.1, "mlt {} path tracing {}", a, b);
+    }
+}
@@ -0,0 +1,124 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use std::{any::Any, cell::RefCell, mem, ops::Range};
+
+thread_local! {
+    static RNG: RefCell<Pcg64Mcg> = RefCell::new(Pcg64Mcg::from_entropy());
+    static PRIMARY: RefCell<Option<Box<dyn PrimarySampler>>> = const { RefCell::new(None) };
+}
+
+/// Source of random numbers that replaces the generator while installed with
+/// [`with_primary_sampler`], such as the primary sample vector of Metropolis
+/// light transport.
+pub trait PrimarySampler: Any {
+    fn next_sample(&mut self) -> f64;
+}
+
+/// Reseeds the random number generator of the current thread.
+///
+/// Every random decision made while tracing a path goes through this module, so
+/// seeding before each sample makes the sample reproducible.
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = Pcg64Mcg::seed_from_u64(seed));
+}
+
+/// Derives a well mixed seed for one sample of one pixel.
+pub fn sample_seed(seed: u64, pixel: u64, sample: u64) -> u64 {
+    let mut h = split_mix(seed);
+    h = split_mix(h ^ pixel);
+    split_mix(h ^ sample)
+}
+
+/// Uniform random number in `[0, 1)`.
+pub fn uniform() -> f64 {
+    PRIMARY.with(|primary| match primary.borrow_mut().as_mut() {
+        Some(sampler) => sampler.next_sample(),
+        None => RNG.with(|rng| rng.borrow_mut().gen::<f64>()),
+    })
+}
+
+/// Runs `f` with every random number on the current thread taken from
+/// `sampler` instead of the generator. The previous source is restored
+/// afterwards, also when `f` panics.
+pub fn with_primary_sampler<S, R>(sampler: &mut S, f: impl FnOnce() -> R) -> R
+where
+    S: PrimarySampler + Default,
+{
+    /// Puts the sampler back when dropped.
+    struct Restore<'a, S: PrimarySampler> {
+        sampler: &'a mut S,
+        previous: Option<Box<dyn PrimarySampler>>,
+    }
+
+    impl<S: PrimarySampler> Drop for Restore<'_, S> {
+        fn drop(&mut self) {
+            let installed = PRIMARY.with(|primary| primary.replace(self.previous.take()));
+            if let Some(sampler) = installed.and_then(|s| (s as Box<dyn Any>).downcast::<S>().ok())
+            {
+                *self.sampler = *sampler;
+            }
+        }
+    }
+
+    let installed: Box<dyn PrimarySampler> = Box::new(mem::take(sampler));
+    let previous = PRIMARY.with(|primary| primary.replace(Some(installed)));
+    let _restore = Restore { sampler, previous };
+    f()
+}
+
+/// Uniform random number in `range`.
+pub fn uniform_range(range: Range<f64>) -> f64 {
+    range.start + (range.end - range.start) * uniform()
+}
+
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_sequence_repeats() {
+        seed(42);
+        let a: Vec<f64> = (0..8).map(|_| uniform()).collect();
+        seed(42);
+        let b: Vec<f64> = (0..8).map(|_| uniform()).collect();
+        assert_eq!(a, b);
+        assert!(a.iter().all(|x| (0.0..1.0).contains(x)));
+    }
+
+    #[derive(Default)]
+    struct Constant(f64);
+
+    impl PrimarySampler for Constant {
+        fn next_sample(&mut self) -> f64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn primary_sampler_is_restored_after_panic() {
+        let mut sampler = Constant(0.25);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_primary_sampler(&mut sampler, || {
+                assert_eq!(uniform(), 0.25);
+                panic!("path failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(sampler.0, 0.25);
+        assert!(PRIMARY.with(|primary| primary.borrow().is_none()));
+    }
+
+    #[test]
+    fn sample_seeds_differ() {
+        assert_ne!(sample_seed(1, 0, 0), sample_seed(1, 0, 1));
+        assert_ne!(sample_seed(1, 0, 0), sample_seed(1, 1, 0));
+        assert_ne!(sample_seed(1, 0, 0), sample_seed(2, 0, 0));
+    }
+}
@@ -0,0 +1,454 @@
+use crate::{
+    camera::projection::{Camera, Projection},
+    material::{medium::MediumStack, scatterable::Scatterable},
+    random,
+    ray::Ray,
+    shape::hittable::Hittable,
+    utility::lerp,
+    vec3::Vec3,
+};
+use rayon::prelude::*;
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Linear framebuffer holding the accumulated radiance and the number of samples
+/// taken for every pixel. Rows are stored top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec3>,
+    pub samples: Vec<u32>,
+}
+
+impl Film {
+    pub fn new(resolution: (u32, u32)) -> Film {
+        let (width, height) = resolution;
+        let n = (width * height) as usize;
+        Film {
+            width,
+            height,
+            pixels: vec![Vec3::origin(); n],
+            samples: vec![0; n],
+        }
+    }
+
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn mean(&self, index: usize) -> Vec3 {
+        match self.samples[index] {
+            0 => Vec3::origin(),
+            n => self.pixels[index] / n as f64,
+        }
+    }
+
+    /// Scales the accumulated radiance of every pixel by `scale`.
+    pub fn expose(&mut self, scale: f64) {
+        self.pixels.iter_mut().for_each(|p| *p *= scale);
+    }
+
+    /// Adds the accumulated samples of `other`, which holds the pixels of `tile`.
+    pub fn merge_tile(&mut self, tile: Tile, other: &Film) {
+        debug_assert_eq!(other.resolution(), (tile.width, tile.height));
+        for row in 0..tile.height {
+            for col in 0..tile.width {
+                let src = (row * tile.width + col) as usize;
+                let dst = ((tile.y + row) * self.width + tile.x + col) as usize;
+                self.pixels[dst] += other.pixels[src];
+                self.samples[dst] += other.samples[src];
+            }
+        }
+    }
+
+    /// Writes the pixels and sample counts as little endian binary.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        for pixel in &self.pixels {
+            for c in *pixel {
+                w.write_all(&c.to_le_bytes())?;
+            }
+        }
+        for count in &self.samples {
+            w.write_all(&count.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a film of the given resolution written by [`Film::write_to`].
+    pub fn read_from(r: &mut impl Read, resolution: (u32, u32)) -> io::Result<Film> {
+        let mut film = Film::new(resolution);
+        let mut buf = [0; 8];
+        for pixel in film.pixels.iter_mut() {
+            for axis in 0..3 {
+                r.read_exact(&mut buf)?;
+                pixel[axis] = f64::from_le_bytes(buf);
+            }
+        }
+        for count in film.samples.iter_mut() {
+            r.read_exact(&mut buf[..4])?;
+            *count = u32::from_le_bytes(buf[..4].try_into().unwrap());
+        }
+        Ok(film)
+    }
+
+    pub fn to_srgb_bytes(&self) -> Vec<u8> {
+        (0..self.pixels.len())
+            .flat_map(|i| to_srgb_bytes(self.mean(i)))
+            .collect()
+    }
+}
+
+pub fn render<T: Hittable + Sync>(
+    resolution: (u32, u32),
+    samples_per_pixel: u32,
+    scene: &T,
+    camera: &Camera,
+) -> Vec<u8> {
+    let full = Tile::full(resolution);
+    render_crop(
+        resolution,
+        full,
+        samples_per_pixel,
+        rand::random(),
+        scene,
+        camera,
+    )
+    .to_srgb_bytes()
+}
+
+/// Renders only the pixels inside the `crop` window of an image with the given
+/// `resolution` and returns a film the size of the window.
+///
+/// To get a full size image with the area outside the window left black, merge
+/// the result into an empty film with [`Film::merge_tile`].
+pub fn render_crop<T: Hittable + Sync>(
+    resolution: (u32, u32),
+    crop: Tile,
+    samples_per_pixel: u32,
+    seed: u64,
+    scene: &T,
+    camera: &Camera,
+) -> Film {
+    assert!(crop.fits(resolution), "crop window outside of image");
+    let mut film = Film::new((crop.width, crop.height));
+    for sample in 0..samples_per_pixel {
+        render_tile_pass(&mut film, crop, resolution, scene, camera, seed, sample);
+    }
+    film
+}
+
+/// Copies the 8 bit RGB pixels of `region`, which covers `tile`, into `image`.
+pub fn composite(image: &mut [u8], resolution: (u32, u32), region: &[u8], tile: Tile) {
+    assert!(tile.fits(resolution), "region outside of image");
+    let row_bytes = 3 * tile.width as usize;
+    for row in 0..tile.height as usize {
+        let src = row * row_bytes;
+        let dst = 3 * ((tile.y as usize + row) * resolution.0 as usize + tile.x as usize);
+        image[dst..dst + row_bytes].copy_from_slice(&region[src..src + row_bytes]);
+    }
+}
+
+/// Rectangular block of pixels in an image, measured from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn full(resolution: (u32, u32)) -> Tile {
+        Tile {
+            x: 0,
+            y: 0,
+            width: resolution.0,
+            height: resolution.1,
+        }
+    }
+
+    /// Whether the tile lies inside an image of the given resolution.
+    pub fn fits(&self, resolution: (u32, u32)) -> bool {
+        let inside =
+            |start: u32, size: u32, end: u32| start.checked_add(size).is_some_and(|e| e <= end);
+        inside(self.x, self.width, resolution.0) && inside(self.y, self.height, resolution.1)
+    }
+
+    /// Splits an image into tiles of at most `size` by `size` pixels.
+    pub fn split(resolution: (u32, u32), size: u32) -> Vec<Tile> {
+        let (width, height) = resolution;
+        (0..height)
+            .step_by(size as usize)
+            .flat_map(|y| {
+                (0..width).step_by(size as usize).map(move |x| Tile {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Adds one sample to every pixel of `film`.
+///
+/// The random sequence of each sample depends only on `seed`, the pixel and
+/// `sample`, so a film built from passes `0..n` is identical no matter how the
+/// passes were split across runs.
+pub fn render_pass<T: Hittable + Sync>(
+    film: &mut Film,
+    scene: &T,
+    camera: &Camera,
+    seed: u64,
+    sample: u32,
+) {
+    let resolution = film.resolution();
+    render_tile_pass(
+        film,
+        Tile::full(resolution),
+        resolution,
+        scene,
+        camera,
+        seed,
+        sample,
+    );
+}
+
+/// Adds one sample to every pixel of `film`, which holds the pixels of `tile` in
+/// an image of the given `resolution`. Samples are seeded by their position in
+/// the full image, so tiles rendered separately match a full render.
+pub fn render_tile_pass<T: Hittable + Sync>(
+    film: &mut Film,
+    tile: Tile,
+    resolution: (u32, u32),
+    scene: &T,
+    camera: &Camera,
+    seed: u64,
+    sample: u32,
+) {
+    debug_assert_eq!(film.resolution(), (tile.width, tile.height));
+    let (width, height) = resolution;
+    let w = width as f64;
+    let h = height as f64;
+
+    film.pixels
+        .par_iter_mut()
+        .zip(film.samples.par_iter_mut())
+        .enumerate()
+        .for_each(|(index, (pixel, count))| {
+            let x = tile.x + index as u32 % tile.width;
+            let y = tile.y + index as u32 / tile.width;
+            let global_index = (y * width + x) as u64;
+            random::seed(random::sample_seed(seed, global_index, sample as u64));
+            let i = x as f64;
+            let j = (height - 1 - y) as f64;
+            let u = (i + random::uniform()) / w;
+            let v = (j + random::uniform()) / h;
+            if let Some((r, weight)) = camera.get_weighted_ray((u, v)) {
+                *pixel += weight * color(&r, scene, 0);
+            }
+            *count += 1;
+        });
+}
+
+pub fn color<T: Hittable>(ray: &Ray, scene: &T, depth: i32) -> Vec3 {
+    let max_depth = 50;
+    let mut ray = *ray;
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    let mut media = MediumStack::default();
+    let mut bounces = depth;
+    while bounces < max_depth {
+        let rec = match scene.hit(&ray, 0.0001..f64::INFINITY) {
+            Some(rec) => rec,
+            None => return throughput * sky(&ray),
+        };
+        if let Some(medium) = media.current() {
+            let (event, weight) = medium.sample_distance(rec.t * ray.direction.length());
+            throughput *= weight;
+            if let Some(distance) = event {
+                // Scatter isotropically inside the medium and carry on the walk
+                // until it leaves through the surface.
+                let point = ray.origin + distance * ray.direction.unitize();
+                ray = Ray::new(point, Vec3::random_unit_vector(), ray.time);
+                // Walks through dense media scatter far more often than paths
+                // bounce off surfaces, so they are not limited by a depth but
+                // ended by Russian roulette once little light is left.
+                let survival = throughput[throughput.max_dim()].min(1.0);
+                if survival < 1.0 {
+                    if random::uniform() >= survival {
+                        return Vec3::origin();
+                    }
+                    throughput /= survival;
+                }
+                continue;
+            }
+        }
+        let eta = match media.relative_ior(&rec) {
+            Some(eta) => eta,
+            None => {
+                // Surfaces inside a medium of higher priority are passed through.
+                media.update(&ray, &ray, &rec);
+                ray.origin = rec.point;
+                bounces += 1;
+                continue;
+            }
+        };
+        let (scattered, attenuation) = match rec.material.scatter_between(&ray, rec, eta) {
+            Some(scatter) => scatter,
+            None => return Vec3::origin(),
+        };
+        media.update(&ray, &scattered, &rec);
+        throughput *= attenuation;
+        ray = scattered;
+        bounces += 1;
+    }
+    Vec3::origin()
+}
+
+fn sky(ray: &Ray) -> Vec3 {
+    let dir = ray.direction.unitize();
+    let t = 0.5 * dir.y + 1.0;
+    lerp(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.5, 0.7, 1.0), t)
+}
+
+pub fn to_srgb_bytes(v: Vec3) -> [u8; 3] {
+    [
+        (linear_srgb_transfer_function(v.x) * 255.99) as u8,
+        (linear_srgb_transfer_function(v.y) * 255.99) as u8,
+        (linear_srgb_transfer_function(v.z) * 255.99) as u8,
+    ]
+}
+
+pub fn linear_srgb_transfer_function(linear: f64) -> f64 {
+    if linear < 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn save_png(path: &Path, size: (u32, u32), buffer: &[u8]) {
+    image::save_buffer(path, buffer, size.0, size.1, image::ColorType::Rgb8).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::bvh::{split_method::Middle, Bvh};
+    use crate::create_scene::create_5_sphere_scene;
+    use crate::material::{dielectric::Dielectric, scatterable::Material, subsurface::Subsurface};
+    use crate::shape::{hittable::Shape, sphere::Sphere};
+
+    #[test]
+    fn crop_matches_full_render() {
+        let resolution = (10, 6);
+        let crop = Tile {
+            x: 3,
+            y: 2,
+            width: 4,
+            height: 3,
+        };
+        let (camera, world) = create_5_sphere_scene(10.0, 6.0);
+        let scene = world.as_slice();
+
+        let full = render_crop(resolution, Tile::full(resolution), 2, 5, &scene, &camera);
+        let cropped = render_crop(resolution, crop, 2, 5, &scene, &camera);
+        let mut uncropped = Film::new(resolution);
+        uncropped.merge_tile(crop, &cropped);
+
+        for y in 0..resolution.1 {
+            for x in 0..resolution.0 {
+                let index = (y * resolution.0 + x) as usize;
+                let inside = (crop.x..crop.x + crop.width).contains(&x)
+                    && (crop.y..crop.y + crop.height).contains(&y);
+                match inside {
+                    true => assert_eq!(uncropped.pixels[index], full.pixels[index]),
+                    false => assert_eq!(uncropped.samples[index], 0),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn absorption_inside_dielectric() {
+        // Without refraction every ray passes straight through the sphere.
+        let glass = Dielectric::new(1.0).with_transmittance(Vec3::new(0.5, 0.8, 1.0), 1.0);
+        let sphere = Sphere::new(Vec3::origin(), 1.0, Material::Dielectric(glass));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let through = color(&ray, &sphere, 0);
+        let expected = sky(&ray) * Vec3::new(0.25, 0.64, 1.0);
+        assert!((through - expected).length() < 1e-9);
+    }
+
+    #[test]
+    fn random_walk_leaves_through_surface() {
+        // A white medium behind an index matched surface loses no light, so
+        // every walk returns the sky in the direction it leaves in.
+        let material = Material::Subsurface(Subsurface::new(
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(0.2, 0.2, 0.2),
+            1.0,
+        ));
+        let mut world = vec![
+            Shape::Sph(Sphere::new(Vec3::origin(), 1.0, material.clone())),
+            Shape::Sph(Sphere::new(Vec3::new(3.0, 0.0, 0.0), 1.0, material)),
+        ];
+        let bvh = Bvh::build(&mut world, Middle);
+        random::seed(41);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        for _ in 0..100 {
+            let c = color(&ray, &bvh, 0);
+            assert!(c.x > 0.25 - 1e-9 && c.x < 0.75 + 1e-9);
+            assert!((c.z - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn dense_walks_are_not_cut_off() {
+        // Light only leaves this medium through the surface, after thousands of
+        // steps for some of the walks.
+        let material = Material::Subsurface(Subsurface::new(
+            Vec3::new(1.0, 1.0, 1.0),
+            Vec3::new(0.02, 0.02, 0.02),
+            1.0,
+        ));
+        let mut world = vec![Shape::Sph(Sphere::new(Vec3::origin(), 1.0, material))];
+        let bvh = Bvh::build(&mut world, Middle);
+        random::seed(42);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        for _ in 0..500 {
+            assert!((color(&ray, &bvh, 0).z - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn composite_region() {
+        let resolution = (4, 3);
+        let mut image = vec![0; 4 * 3 * 3];
+        let tile = Tile {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        composite(&mut image, resolution, &[255; 2 * 2 * 3], tile);
+        let lit: Vec<usize> = (0..12).filter(|&i| image[3 * i] == 255).collect();
+        assert_eq!(lit, vec![5, 6, 9, 10]);
+    }
+
+    #[test]
+    fn tile_fits_without_overflow() {
+        let tile = Tile {
+            x: u32::MAX,
+            y: 0,
+            width: 2,
+            height: 1,
+        };
+        assert!(!tile.fits((4, 3)));
+        assert!(Tile::full((4, 3)).fits((4, 3)));
+    }
+}
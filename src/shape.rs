@@ -1,3 +1,5 @@
 pub mod hittable;
+pub mod instance;
+pub mod moving_sphere;
 pub mod sphere;
 pub mod triangle_mesh;
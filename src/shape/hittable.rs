@@ -1,3 +1,5 @@
+use super::instance::Instance;
+use super::moving_sphere::MovingSphere;
 use super::sphere::Sphere;
 use super::triangle_mesh::Triangle;
 use crate::{
@@ -6,16 +8,30 @@ use crate::{
 use enum_dispatch::enum_dispatch;
 use std::ops::Range;
 
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub t: f64,
     pub point: Vec3,
+    /// Normal used for shading, which normal and bump maps may tilt away from
+    /// the surface.
     pub normal: Vec3,
+    /// Normal of the actual surface, pointing to the same side as `normal`.
+    pub geometric_normal: Vec3,
+    /// Texture coordinates of the hit point.
+    pub uv: (f64, f64),
+    /// Weights of the second and third vertex of a triangle, zero for other
+    /// shapes.
+    pub barycentric: (f64, f64),
+    /// Derivatives of the point with respect to the texture coordinates,
+    /// tangent to the surface.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub material: &'a Material,
 }
 
 #[enum_dispatch(Shape)]
 pub trait Hittable: std::fmt::Debug {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>>;
     fn bounding_box(&self) -> AxisAlignedBoundingBox;
 }
 
@@ -24,13 +40,15 @@ pub trait Hittable: std::fmt::Debug {
 pub enum Shape<'a, 'b> {
     Sph(Sphere),
     Triangle(Triangle<'a, 'b>),
+    MovingSphere(MovingSphere),
+    Instance(Instance<'a, 'b>),
 }
 
 impl<T> Hittable for &[T]
 where
     T: Hittable,
 {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         let mut closest = t_range.end;
         let mut record: Option<HitRecord> = None;
         for item in self.iter() {
@@ -55,7 +73,7 @@ impl<T> Hittable for &mut [T]
 where
     T: Hittable,
 {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         let mut closest = t_range.end;
         let mut record: Option<HitRecord> = None;
         for item in self.iter() {
@@ -80,10 +98,10 @@ impl<T> Hittable for Vec<T>
 where
     T: Hittable,
 {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         let mut closest = t_range.end;
         let mut record: Option<HitRecord> = None;
-        for item in self.into_iter() {
+        for item in self.iter() {
             record = match item.hit(ray, t_range.start..closest) {
                 Some(rec) => {
                     closest = rec.t;
@@ -100,7 +118,7 @@ where
 }
 
 impl Hittable for Box<dyn Hittable> {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         (**self).hit(ray, t_range)
     }
     fn bounding_box(&self) -> AxisAlignedBoundingBox {
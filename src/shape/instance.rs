@@ -0,0 +1,159 @@
+use super::hittable::{HitRecord, Hittable, Shape};
+use crate::animation::Track;
+use crate::bvh::aabb::AxisAlignedBoundingBox;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::vec3::Vec3;
+use std::ops::Range;
+
+/// Shape placed in the scene by a transform that may change over time. Rays are
+/// moved into the space of the shape using the transform at the ray's time.
+#[derive(Debug)]
+pub struct Instance<'a, 'b> {
+    pub shape: Box<Shape<'a, 'b>>,
+    pub transform: Track<Transform>,
+    pub shutter: Range<f64>,
+}
+
+impl<'a, 'b> Instance<'a, 'b> {
+    pub fn new(
+        shape: Shape<'a, 'b>,
+        transform: Track<Transform>,
+        shutter: Range<f64>,
+    ) -> Instance<'a, 'b> {
+        Instance {
+            shape: Box::new(shape),
+            transform,
+            shutter,
+        }
+    }
+}
+
+impl<'a, 'b> Hittable for Instance<'a, 'b> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
+        let transform = self.transform.evaluate(ray.time);
+        // The transform scales uniformly, so distances along the unit length local
+        // ray are the world distances divided by the scale.
+        let local_ray = Ray::new(
+            transform.inverse_point(ray.origin),
+            transform.inverse_vector(ray.direction),
+            ray.time,
+        );
+        let scale = transform.scale;
+        let rec = self
+            .shape
+            .hit(&local_ray, t_range.start / scale..t_range.end / scale)?;
+        Some(HitRecord {
+            t: rec.t * scale,
+            point: transform.apply_point(rec.point),
+            normal: transform.apply_normal(rec.normal),
+            geometric_normal: transform.apply_normal(rec.geometric_normal),
+            dpdu: transform.apply_vector(rec.dpdu),
+            dpdv: transform.apply_vector(rec.dpdv),
+            ..rec
+        })
+    }
+
+    /// Bounds the shape over the shutter interval. Translation and scale are
+    /// linear in the transform, so transforming the corners of the local bounds
+    /// by the hull of the transform track bounds the motion. A rotating shape is
+    /// bounded by the sphere around its local origin that encloses it.
+    fn bounding_box(&self) -> AxisAlignedBoundingBox {
+        let local = self.shape.bounding_box();
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                Vec3::new(
+                    [local.min.x, local.max.x][i & 1],
+                    [local.min.y, local.max.y][(i >> 1) & 1],
+                    [local.min.z, local.max.z][(i >> 2) & 1],
+                )
+            })
+            .collect();
+        let hull = self.transform.hull(self.shutter.clone());
+
+        if hull.iter().all(|t| t.rotation == hull[0].rotation) {
+            return AxisAlignedBoundingBox::from_points(
+                hull.iter()
+                    .flat_map(|t| corners.iter().map(move |c| t.apply_point(*c))),
+            );
+        }
+
+        let max_scale = hull.iter().map(|t| t.scale).fold(0.0, f64::max);
+        let radius = max_scale * corners.iter().map(|c| c.length()).fold(0.0, f64::max);
+        AxisAlignedBoundingBox::from_boxes(hull.iter().map(|t| AxisAlignedBoundingBox {
+            min: t.translation - radius,
+            max: t.translation + radius,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Interpolation, Keyframe};
+    use crate::material::lambertian::Lambertian;
+    use crate::material::scatterable::Material;
+    use crate::shape::sphere::Sphere;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn instance(rotation: Vec3) -> Instance<'static, 'static> {
+        let sphere = Sphere::new(
+            Vec3::new(1.0, 0.0, 0.0),
+            0.5,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+            }),
+        );
+        let transform = Track::new(
+            vec![
+                Keyframe {
+                    time: 0.0,
+                    value: Transform {
+                        scale: 2.0,
+                        ..Transform::identity()
+                    },
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: Transform {
+                        translation: Vec3::new(0.0, 0.0, -4.0),
+                        rotation,
+                        scale: 2.0,
+                    },
+                },
+            ],
+            Interpolation::Linear,
+        );
+        Instance::new(Shape::Sph(sphere), transform, 0.0..1.0)
+    }
+
+    #[test]
+    fn hit_uses_world_distances() {
+        let instance = instance(Vec3::origin());
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = instance.hit(&ray, 0.0..f64::INFINITY).unwrap();
+        assert_approx_eq!(rec.t, 4.0);
+        assert_approx_eq!((rec.point - Vec3::new(2.0, 0.0, 1.0)).length(), 0.0);
+        assert_approx_eq!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length(), 0.0);
+
+        let ray = Ray { time: 1.0, ..ray };
+        let rec = instance.hit(&ray, 0.0..f64::INFINITY).unwrap();
+        assert_approx_eq!(rec.t, 8.0);
+    }
+
+    #[test]
+    fn bounding_box_covers_shutter() {
+        for rotation in [Vec3::origin(), Vec3::new(0.0, 90.0, 0.0)] {
+            let instance = instance(rotation);
+            let bounds = instance.bounding_box();
+            for i in 0..=10 {
+                let t = instance.transform.evaluate(i as f64 / 10.0);
+                let center = t.apply_point(Vec3::new(1.0, 0.0, 0.0));
+                for axis in 0..3 {
+                    assert!(bounds.min[axis] <= center[axis] - 1.0 + 1e-9);
+                    assert!(bounds.max[axis] >= center[axis] + 1.0 - 1e-9);
+                }
+            }
+        }
+    }
+}
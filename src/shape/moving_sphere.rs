@@ -0,0 +1,95 @@
+use super::hittable::{HitRecord, Hittable};
+use super::sphere::hit_sphere;
+use crate::animation::Track;
+use crate::bvh::aabb::AxisAlignedBoundingBox;
+use crate::material::scatterable::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+use std::ops::Range;
+
+/// Sphere whose center follows a keyframe track, intersected at the time of
+/// each ray. The bounding box covers the motion during `shutter`.
+#[derive(Debug, Clone)]
+pub struct MovingSphere {
+    pub center: Track<Vec3>,
+    pub radius: f64,
+    pub material: Material,
+    pub shutter: Range<f64>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center: Track<Vec3>,
+        radius: f64,
+        material: Material,
+        shutter: Range<f64>,
+    ) -> MovingSphere {
+        MovingSphere {
+            center,
+            radius,
+            material,
+            shutter,
+        }
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
+        let center = self.center.evaluate(ray.time);
+        hit_sphere(center, self.radius, &self.material, ray, t_range)
+    }
+
+    fn bounding_box(&self) -> AxisAlignedBoundingBox {
+        let r = self.radius.abs();
+        AxisAlignedBoundingBox::from_boxes(self.center.hull(self.shutter.clone()).into_iter().map(
+            |center| AxisAlignedBoundingBox {
+                min: center - r,
+                max: center + r,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Interpolation, Keyframe};
+    use crate::material::lambertian::Lambertian;
+
+    #[test]
+    fn hit_follows_time() {
+        let center = Track::new(
+            vec![
+                Keyframe {
+                    time: 0.0,
+                    value: Vec3::new(0.0, 0.0, 0.0),
+                },
+                Keyframe {
+                    time: 1.0,
+                    value: Vec3::new(0.0, 2.0, 0.0),
+                },
+            ],
+            Interpolation::Linear,
+        );
+        let sphere = MovingSphere::new(
+            center,
+            0.5,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+            }),
+            0.0..1.0,
+        );
+        let origin = Vec3::new(-2.0, 2.0, 0.0);
+        let direction = Vec3::new(1.0, 0.0, 0.0);
+        assert!(sphere
+            .hit(&Ray::new(origin, direction, 0.0), 0.0..f64::INFINITY)
+            .is_none());
+        assert!(sphere
+            .hit(&Ray::new(origin, direction, 1.0), 0.0..f64::INFINITY)
+            .is_some());
+
+        let bounds = sphere.bounding_box();
+        assert_eq!(bounds.min, Vec3::new(-0.5, -0.5, -0.5));
+        assert_eq!(bounds.max, Vec3::new(0.5, 2.5, 0.5));
+    }
+}
@@ -2,10 +2,12 @@ use super::hittable::{HitRecord, Hittable};
 use crate::bvh::aabb::AxisAlignedBoundingBox;
 use crate::material::scatterable::Material;
 use crate::ray::Ray;
+use crate::utility::coordinate_system;
 use crate::vec3::Vec3;
+use std::f64::consts::{PI, TAU};
 use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
@@ -23,32 +25,8 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
-        let oc = ray.origin - self.center;
-        let b = Vec3::dot(oc, ray.direction);
-        let c = Vec3::dot(oc, oc) - self.radius * self.radius;
-        let mut disc = b * b - c;
-        if disc <= 0.0 {
-            return None;
-        }
-
-        disc = disc.sqrt();
-        let mut t = -b - disc;
-        if !t_range.contains(&t) {
-            t = -b + disc;
-            if !t_range.contains(&t) {
-                return None;
-            }
-        }
-
-        let point = ray.evaluate(t);
-        let normal = (point - self.center) / self.radius;
-        Some(HitRecord {
-            t,
-            point,
-            normal,
-            material: &self.material,
-        })
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center, self.radius, &self.material, ray, t_range)
     }
 
     fn bounding_box(&self) -> AxisAlignedBoundingBox {
@@ -58,3 +36,96 @@ impl Hittable for Sphere {
         }
     }
 }
+
+pub(crate) fn hit_sphere<'a>(
+    center: Vec3,
+    radius: f64,
+    material: &'a Material,
+    ray: &Ray,
+    t_range: Range<f64>,
+) -> Option<HitRecord<'a>> {
+    let oc = ray.origin - center;
+    let b = Vec3::dot(oc, ray.direction);
+    let c = Vec3::dot(oc, oc) - radius * radius;
+    let mut disc = b * b - c;
+    if disc <= 0.0 {
+        return None;
+    }
+
+    disc = disc.sqrt();
+    let mut t = -b - disc;
+    if !t_range.contains(&t) {
+        t = -b + disc;
+        if !t_range.contains(&t) {
+            return None;
+        }
+    }
+
+    let point = ray.evaluate(t);
+    let normal = (point - center) / radius;
+    let (dpdu, dpdv) = sphere_tangents(normal * radius.signum(), radius.abs());
+    Some(HitRecord {
+        t,
+        point,
+        normal,
+        geometric_normal: normal,
+        uv: sphere_uv(normal * radius.signum()),
+        barycentric: (0.0, 0.0),
+        dpdu,
+        dpdv,
+        material,
+    })
+}
+
+/// Longitude and latitude of the point `p` on the unit sphere, with u growing
+/// from -x through -z, and v from the bottom to the top.
+fn sphere_uv(p: Vec3) -> (f64, f64) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = f64::atan2(-p.z, p.x) + PI;
+    (phi / TAU, theta / PI)
+}
+
+/// Derivatives of the point on a sphere of `radius` in direction `p` from its
+/// center with respect to the coordinates of [`sphere_uv`]. The parametrization
+/// is singular at the poles, where any tangents are used.
+fn sphere_tangents(p: Vec3, radius: f64) -> (Vec3, Vec3) {
+    let sin_theta = p.x.hypot(p.z);
+    if sin_theta < 1e-9 {
+        return coordinate_system(p);
+    }
+    (
+        TAU * radius * Vec3::new(p.z, 0.0, -p.x),
+        PI * radius * Vec3::new(-p.x * p.y / sin_theta, sin_theta, -p.y * p.z / sin_theta),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::lambertian::Lambertian;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn tangents_follow_uv() {
+        let sphere = Sphere::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            2.0,
+            Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.5, 0.5, 0.5).into(),
+            }),
+        );
+        let direction = Vec3::new(-0.3, -0.5, -1.0);
+        let ray = Ray::new(sphere.center - 5.0 * direction, direction, 0.0);
+        let rec = sphere.hit(&ray, 0.0..f64::INFINITY).unwrap();
+        assert!(Vec3::cross(rec.dpdu, rec.dpdv).dot(rec.normal) > 0.0);
+
+        let eps = 1e-6;
+        let uv_at = |p: Vec3| sphere_uv((p - sphere.center).unitize());
+        let (u, v) = uv_at(rec.point + eps * rec.dpdu);
+        assert_approx_eq!((u - rec.uv.0) / eps, 1.0, 1e-3);
+        assert_approx_eq!((v - rec.uv.1) / eps, 0.0, 1e-3);
+        let (u, v) = uv_at(rec.point + eps * rec.dpdv);
+        assert_approx_eq!((u - rec.uv.0) / eps, 0.0, 1e-3);
+        assert_approx_eq!((v - rec.uv.1) / eps, 1.0, 1e-3);
+    }
+}
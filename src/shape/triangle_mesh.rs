@@ -2,23 +2,33 @@ use super::hittable::{HitRecord, Hittable};
 use crate::bvh::aabb::AxisAlignedBoundingBox;
 use crate::material::scatterable::Material;
 use crate::ray::Ray;
+use crate::utility::coordinate_system;
 use crate::vec3::Vec3;
 use itertools::Itertools;
 use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TriangleMesh {
     pub vertices: Vec<Vec3>,
     pub vertex_indices: Vec<[usize; 3]>,
+    /// Texture coordinates, indexed by `uv_indices` which has one entry per
+    /// triangle. Without them the vertices of every triangle get the
+    /// coordinates (0, 0), (1, 0) and (1, 1).
+    pub uvs: Vec<(f64, f64)>,
+    pub uv_indices: Vec<[usize; 3]>,
     pub material: Material,
 }
 
 impl TriangleMesh {
-    pub fn triangles(&self) -> impl Iterator<Item = Triangle> {
-        self.vertex_indices.iter().map(|indices| Triangle {
-            mesh: self,
-            indices,
-        })
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle<'_, '_>> {
+        self.vertex_indices
+            .iter()
+            .enumerate()
+            .map(|(index, indices)| Triangle {
+                mesh: self,
+                indices,
+                index,
+            })
     }
 }
 
@@ -26,6 +36,8 @@ impl TriangleMesh {
 pub struct Triangle<'a, 'b> {
     pub mesh: &'a TriangleMesh,
     pub indices: &'b [usize; 3],
+    /// Position of the triangle in the mesh.
+    pub index: usize,
 }
 
 impl<'a, 'b> Triangle<'a, 'b> {
@@ -33,6 +45,13 @@ impl<'a, 'b> Triangle<'a, 'b> {
         self.indices.map(|i| self.mesh.vertices[i])
     }
 
+    pub fn get_uvs(&self) -> [(f64, f64); 3] {
+        match self.mesh.uv_indices.get(self.index) {
+            Some(indices) => indices.map(|i| self.mesh.uvs[i]),
+            None => [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+        }
+    }
+
     pub fn area(&self) -> f64 {
         let vertices = self.get_vertices();
         Vec3::cross(vertices[1] - vertices[0], vertices[2] - vertices[0]).length() / 2.0
@@ -40,7 +59,7 @@ impl<'a, 'b> Triangle<'a, 'b> {
 }
 
 impl<'a, 'b> Hittable for Triangle<'a, 'b> {
-    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_range: Range<f64>) -> Option<HitRecord<'_>> {
         let v = self.get_vertices();
         let v0v1 = v[1] - v[0];
         let v0v2 = v[2] - v[0];
@@ -72,10 +91,26 @@ impl<'a, 'b> Hittable for Triangle<'a, 'b> {
             return None;
         }
 
+        // Barycentric weights of the second and third vertex.
+        let n_sq = n.length_sq();
+        let b1 = n.dot(Vec3::cross(v[0] - v[2], p - v[2])) / n_sq;
+        let b2 = n.dot(Vec3::cross(v0v1, p - v[0])) / n_sq;
+        let b0 = 1.0 - b1 - b2;
+
+        let uv = self.get_uvs();
+        let (dpdu, dpdv) = triangle_tangents(v, uv, n);
         Some(HitRecord {
             material: &self.mesh.material,
             normal: n.unitize(),
+            geometric_normal: n.unitize(),
             point: p,
+            uv: (
+                b0 * uv[0].0 + b1 * uv[1].0 + b2 * uv[2].0,
+                b0 * uv[0].1 + b1 * uv[1].1 + b2 * uv[2].1,
+            ),
+            barycentric: (b1, b2),
+            dpdu,
+            dpdv,
             t,
         })
     }
@@ -86,17 +121,37 @@ impl<'a, 'b> Hittable for Triangle<'a, 'b> {
     }
 }
 
+/// Derivatives of the point on the triangle with the vertices `v` and texture
+/// coordinates `uv` with respect to the texture coordinates. Degenerate texture
+/// coordinates give any tangents of the plane with normal `n`.
+fn triangle_tangents(v: [Vec3; 3], uv: [(f64, f64); 3], n: Vec3) -> (Vec3, Vec3) {
+    let duv02 = (uv[0].0 - uv[2].0, uv[0].1 - uv[2].1);
+    let duv12 = (uv[1].0 - uv[2].0, uv[1].1 - uv[2].1);
+    let dp02 = v[0] - v[2];
+    let dp12 = v[1] - v[2];
+    let determinant = duv02.0 * duv12.1 - duv02.1 * duv12.0;
+    if determinant.abs() < 1e-12 {
+        return coordinate_system(n.unitize());
+    }
+    let inv = 1.0 / determinant;
+    (
+        (duv12.1 * dp02 - duv02.1 * dp12) * inv,
+        (duv02.0 * dp12 - duv12.0 * dp02) * inv,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::material::lambertian::Lambertian;
     use crate::material::scatterable::Material;
+    use assert_approx_eq::assert_approx_eq;
 
     #[test]
     fn test_hit() {
         let mesh = TriangleMesh {
             material: Material::Lambertian(Lambertian {
-                albedo: Vec3::new(0.7, 0.7, 0.7),
+                albedo: Vec3::new(0.7, 0.7, 0.7).into(),
             }),
             vertex_indices: vec![[0, 1, 2]],
             vertices: vec![
@@ -104,10 +159,42 @@ mod tests {
                 Vec3::new(1.0, 0.0, 1.0),
                 Vec3::new(1.0, 1.0, 0.0),
             ],
+            uvs: vec![],
+            uv_indices: vec![],
         };
         let tri = mesh.triangles().collect::<Vec<_>>()[0];
-        let ray = Ray::new(Vec3::new(0.0, 1.2, 0.2), Vec3::new(1.0, 0.0, 0.0).unitize());
+        let ray = Ray::new(
+            Vec3::new(0.0, 1.2, 0.2),
+            Vec3::new(1.0, 0.0, 0.0).unitize(),
+            0.0,
+        );
         let h = tri.hit(&ray, 0.0..f64::INFINITY);
         assert!(h.is_none());
     }
+
+    #[test]
+    fn interpolates_uvs() {
+        let mesh = TriangleMesh {
+            material: Material::Lambertian(Lambertian {
+                albedo: Vec3::new(0.7, 0.7, 0.7).into(),
+            }),
+            vertex_indices: vec![[0, 1, 2]],
+            vertices: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 4.0, 0.0),
+            ],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            uv_indices: vec![[0, 1, 2]],
+        };
+        let tri = mesh.triangles().next().unwrap();
+        let ray = Ray::new(Vec3::new(0.5, 1.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = tri.hit(&ray, 0.0..f64::INFINITY).unwrap();
+        assert_approx_eq!(rec.barycentric.0, 0.25);
+        assert_approx_eq!(rec.barycentric.1, 0.25);
+        assert_approx_eq!(rec.uv.0, 0.25);
+        assert_approx_eq!(rec.uv.1, 0.25);
+        assert_approx_eq!((rec.dpdu - Vec3::new(2.0, 0.0, 0.0)).length(), 0.0);
+        assert_approx_eq!((rec.dpdv - Vec3::new(0.0, 4.0, 0.0)).length(), 0.0);
+    }
 }
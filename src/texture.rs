@@ -0,0 +1,5 @@
+pub mod checker;
+pub mod constant;
+pub mod image_texture;
+pub mod noise;
+pub mod texture_kind;
@@ -0,0 +1,51 @@
+use super::texture_kind::{Texture, TextureKind};
+use crate::vec3::Vec3;
+
+/// Solid checker pattern of cubes with sides of `size`, alternating between
+/// the `even` and `odd` textures.
+#[derive(Debug, Clone)]
+pub struct Checker {
+    pub even: Box<TextureKind>,
+    pub odd: Box<TextureKind>,
+    pub size: f64,
+}
+
+impl Checker {
+    pub fn new(even: impl Into<TextureKind>, odd: impl Into<TextureKind>, size: f64) -> Checker {
+        Checker {
+            even: Box::new(even.into()),
+            odd: Box::new(odd.into()),
+            size,
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, uv: (f64, f64), point: Vec3) -> Vec3 {
+        let cell = point
+            .into_iter()
+            .map(|c| (c / self.size).floor() as i64)
+            .sum::<i64>();
+        match cell.rem_euclid(2) {
+            0 => self.even.value(uv, point),
+            _ => self.odd.value(uv, point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternates_between_cells() {
+        let black = Vec3::origin();
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let checker = Checker::new(white, black, 0.5);
+        let value = |x, y, z| checker.value((0.0, 0.0), Vec3::new(x, y, z));
+        assert_eq!(value(0.1, 0.1, 0.1), white);
+        assert_eq!(value(0.6, 0.1, 0.1), black);
+        assert_eq!(value(-0.1, 0.1, 0.1), black);
+        assert_eq!(value(0.6, 0.6, 0.1), white);
+    }
+}
@@ -0,0 +1,13 @@
+use super::texture_kind::Texture;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub color: Vec3,
+}
+
+impl Texture for Constant {
+    fn value(&self, _uv: (f64, f64), _point: Vec3) -> Vec3 {
+        self.color
+    }
+}
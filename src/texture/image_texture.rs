@@ -0,0 +1,74 @@
+use super::texture_kind::Texture;
+use crate::{linear_image::LinearImage, vec3::Vec3};
+use image::ImageResult;
+use std::{path::Path, sync::Arc};
+
+/// Image mapped onto the surface by its texture coordinates, with `(0, 0)` at
+/// the lower left corner of the image. The image repeats outside the unit
+/// square and is sampled with bilinear filtering.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Arc<LinearImage>,
+}
+
+impl ImageTexture {
+    pub fn load(path: &Path) -> ImageResult<ImageTexture> {
+        Ok(ImageTexture {
+            image: Arc::new(LinearImage::load(path)?),
+        })
+    }
+
+    /// Loads an image holding data rather than color, such as a normal map,
+    /// without decoding it as sRGB.
+    pub fn load_data(path: &Path) -> ImageResult<ImageTexture> {
+        Ok(ImageTexture {
+            image: Arc::new(LinearImage::load_data(path)?),
+        })
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Vec3 {
+        let (width, height) = (self.image.width as i64, self.image.height as i64);
+        let x = x.rem_euclid(width);
+        let y = y.rem_euclid(height);
+        self.image.pixels[(y * width + x) as usize]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: (f64, f64), _point: Vec3) -> Vec3 {
+        // Rows are stored top to bottom, so v is flipped.
+        let x = uv.0 * self.image.width as f64 - 0.5;
+        let y = (1.0 - uv.1) * self.image.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn samples_texel_centers() {
+        let red = Vec3::new(1.0, 0.0, 0.0);
+        let blue = Vec3::new(0.0, 0.0, 1.0);
+        let texture = ImageTexture {
+            image: Arc::new(LinearImage {
+                width: 2,
+                height: 1,
+                pixels: vec![red, blue],
+            }),
+        };
+        let value = |u| texture.value((u, 0.5), Vec3::origin());
+        assert_eq!(value(0.25), red);
+        assert_eq!(value(0.75), blue);
+        assert_approx_eq!((value(0.5) - Vec3::new(0.5, 0.0, 0.5)).length(), 0.0);
+        // Wraps around at the edges.
+        assert_approx_eq!((value(1.25) - red).length(), 0.0);
+    }
+}
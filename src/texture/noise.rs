@@ -0,0 +1,125 @@
+use super::texture_kind::Texture;
+use crate::{utility::lerp, vec3::Vec3};
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use std::sync::Arc;
+
+/// Gradient noise in three dimensions (Perlin 2002).
+#[derive(Debug)]
+pub struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut Pcg64Mcg::seed_from_u64(seed));
+        let mut permutation = [0; 512];
+        for (i, p) in permutation.iter_mut().enumerate() {
+            *p = table[i % 256];
+        }
+        Perlin { permutation }
+    }
+
+    /// Noise value in about `[-1, 1]`, zero at integer lattice points.
+    pub fn noise(&self, point: Vec3) -> f64 {
+        let cell = [point.x.floor(), point.y.floor(), point.z.floor()];
+        let [x, y, z] = [point.x - cell[0], point.y - cell[1], point.z - cell[2]];
+        let [i, j, k] = cell.map(|c| (c as i64).rem_euclid(256) as usize);
+        let p = &self.permutation;
+        let hash =
+            |di: usize, dj: usize, dk: usize| p[p[p[i + di] as usize + j + dj] as usize + k + dk];
+
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let corner = |di: usize, dj: usize, dk: usize| {
+            gradient(
+                hash(di, dj, dk),
+                x - di as f64,
+                y - dj as f64,
+                z - dk as f64,
+            )
+        };
+        lerp(
+            lerp(
+                lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+                lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+                v,
+            ),
+            lerp(
+                lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+                lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+                v,
+            ),
+            w,
+        )
+    }
+
+    /// Fractional Brownian motion, the sum of `octaves` layers of noise each
+    /// twice the frequency and half the amplitude of the previous one.
+    pub fn fbm(&self, point: Vec3, octaves: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(frequency * point);
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn gradient(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Solid noise blending from `low` to `high`, with features of about `1 /
+/// frequency` in size. One octave gives plain Perlin noise, more add detail.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub perlin: Arc<Perlin>,
+    pub frequency: f64,
+    pub octaves: u32,
+    pub low: Vec3,
+    pub high: Vec3,
+}
+
+impl Texture for Noise {
+    fn value(&self, _uv: (f64, f64), point: Vec3) -> Vec3 {
+        let n = self.perlin.fbm(self.frequency * point, self.octaves);
+        lerp(self.low, self.high, (0.5 * (n + 1.0)).clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(Vec3::new(3.0, -2.0, 7.0)), 0.0);
+        let mut previous = perlin.noise(Vec3::new(0.5, 0.5, 0.5));
+        for i in 1..1000 {
+            let p = Vec3::new(0.5 + i as f64 * 0.001, 0.5, 0.5);
+            let n = perlin.noise(p);
+            assert!(n.abs() <= 1.5);
+            assert!((n - previous).abs() < 0.01);
+            previous = n;
+        }
+        assert!(perlin.fbm(Vec3::new(0.3, 0.7, 0.1), 4).abs() <= 3.0);
+    }
+}
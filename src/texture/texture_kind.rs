@@ -0,0 +1,24 @@
+use super::{checker::Checker, constant::Constant, image_texture::ImageTexture, noise::Noise};
+use crate::vec3::Vec3;
+use enum_dispatch::enum_dispatch;
+
+#[enum_dispatch(TextureKind)]
+pub trait Texture: std::fmt::Debug {
+    /// Colour at the surface point `point` with texture coordinates `uv`.
+    fn value(&self, uv: (f64, f64), point: Vec3) -> Vec3;
+}
+
+#[enum_dispatch]
+#[derive(Debug, Clone)]
+pub enum TextureKind {
+    Constant,
+    Checker,
+    ImageTexture,
+    Noise,
+}
+
+impl From<Vec3> for TextureKind {
+    fn from(color: Vec3) -> Self {
+        TextureKind::Constant(Constant { color })
+    }
+}
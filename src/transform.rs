@@ -0,0 +1,133 @@
+use crate::vec3::Vec3;
+use std::ops::{Add, Mul};
+
+/// Similarity transform applied as scale, then rotation about the x, y and z
+/// axes in that order, then translation. Rotation angles are in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: f64,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            translation: Vec3::origin(),
+            rotation: Vec3::origin(),
+            scale: 1.0,
+        }
+    }
+
+    pub fn translate(translation: Vec3) -> Transform {
+        Transform {
+            translation,
+            ..Transform::identity()
+        }
+    }
+
+    pub fn rotate(rotation: Vec3) -> Transform {
+        Transform {
+            rotation,
+            ..Transform::identity()
+        }
+    }
+
+    pub fn apply_point(&self, p: Vec3) -> Vec3 {
+        self.apply_vector(p) + self.translation
+    }
+
+    pub fn apply_vector(&self, v: Vec3) -> Vec3 {
+        self.rotate_vector(v * self.scale)
+    }
+
+    pub fn apply_normal(&self, n: Vec3) -> Vec3 {
+        self.rotate_vector(n)
+    }
+
+    pub fn inverse_point(&self, p: Vec3) -> Vec3 {
+        self.inverse_vector(p - self.translation)
+    }
+
+    pub fn inverse_vector(&self, v: Vec3) -> Vec3 {
+        self.inverse_rotate_vector(v) / self.scale
+    }
+
+    fn rotate_vector(&self, v: Vec3) -> Vec3 {
+        let r = self.rotation;
+        let v = rotate_axis(v, 1, 2, r.x.to_radians());
+        let v = rotate_axis(v, 2, 0, r.y.to_radians());
+        rotate_axis(v, 0, 1, r.z.to_radians())
+    }
+
+    fn inverse_rotate_vector(&self, v: Vec3) -> Vec3 {
+        let r = self.rotation;
+        let v = rotate_axis(v, 0, 1, -r.z.to_radians());
+        let v = rotate_axis(v, 2, 0, -r.y.to_radians());
+        rotate_axis(v, 1, 2, -r.x.to_radians())
+    }
+}
+
+/// Rotates `v` by `angle` in the plane spanned by the axes `a` and `b`.
+fn rotate_axis(v: Vec3, a: usize, b: usize, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    let mut r = v;
+    r[a] = cos * v[a] - sin * v[b];
+    r[b] = sin * v[a] + cos * v[b];
+    r
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Add<Transform> for Transform {
+    type Output = Transform;
+
+    fn add(self, rhs: Transform) -> Transform {
+        Transform {
+            translation: self.translation + rhs.translation,
+            rotation: self.rotation + rhs.rotation,
+            scale: self.scale + rhs.scale,
+        }
+    }
+}
+
+impl Mul<f64> for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: f64) -> Transform {
+        Transform {
+            translation: self.translation * rhs,
+            rotation: self.rotation * rhs,
+            scale: self.scale * rhs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn inverse_undoes_transform() {
+        let transform = Transform {
+            translation: Vec3::new(1.0, -2.0, 0.5),
+            rotation: Vec3::new(30.0, 45.0, -60.0),
+            scale: 2.0,
+        };
+        let p = Vec3::new(0.3, 0.7, -1.1);
+        let q = transform.inverse_point(transform.apply_point(p));
+        assert_approx_eq!((p - q).length(), 0.0, 1e-9);
+    }
+
+    #[test]
+    fn rotate_about_y() {
+        let transform = Transform::rotate(Vec3::new(0.0, 90.0, 0.0));
+        let p = transform.apply_point(Vec3::new(1.0, 0.0, 0.0));
+        assert_approx_eq!((p - Vec3::new(0.0, 0.0, -1.0)).length(), 0.0, 1e-9);
+    }
+}
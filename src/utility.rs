@@ -1,5 +1,23 @@
+use crate::vec3::Vec3;
 use std::ops::{Add, Mul};
 
 pub fn lerp<T: Add<T, Output = T> + Mul<f64, Output = T>>(a: T, b: T, t: f64) -> T {
     a * (1.0 - t) + b * t
 }
+
+/// Relative luminance of a linear sRGB colour.
+pub fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+/// Two unit vectors forming an orthonormal basis with the unit vector `v`
+/// (Duff et al. 2017).
+pub fn coordinate_system(v: Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0f64.copysign(v.z);
+    let a = -1.0 / (sign + v.z);
+    let b = v.x * v.y * a;
+    (
+        Vec3::new(1.0 + sign * v.x * v.x * a, sign * b, -sign * v.x),
+        Vec3::new(b, sign + v.y * v.y * a, -v.y),
+    )
+}
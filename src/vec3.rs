@@ -1,8 +1,7 @@
-use rand::Rng;
+use crate::random;
 use std::array::IntoIter;
 use std::cmp::Ordering;
 use std::{
-    default::Default,
     f64::consts::TAU,
     iter::IntoIterator,
     ops::{
@@ -22,7 +21,7 @@ macro_rules! debug_assert_approx_eq {
     }};
 }
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Default)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -89,7 +88,7 @@ impl Vec3 {
     pub fn reflect(&self, normal: Vec3) -> Vec3 {
         debug_assert_approx_eq!(normal.length(), 1.0, 1e-3f64);
 
-        return *self - 2.0 * Vec3::dot(*self, normal) * normal;
+        *self - 2.0 * Vec3::dot(*self, normal) * normal
     }
 
     pub fn refract(&self, normal: Vec3, ior_ratio: f64) -> Option<Vec3> {
@@ -105,18 +104,16 @@ impl Vec3 {
     }
 
     pub fn random(range: Range<f64>) -> Vec3 {
-        let mut rng = rand::thread_rng();
         Vec3::new(
-            rng.gen_range(range.clone()),
-            rng.gen_range(range.clone()),
-            rng.gen_range(range),
+            random::uniform_range(range.clone()),
+            random::uniform_range(range.clone()),
+            random::uniform_range(range),
         )
     }
 
     pub fn random_unit_vector() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let phi = rng.gen_range(0.0..TAU);
-        let z: f64 = rng.gen_range(-1.0..1.0);
+        let phi = random::uniform_range(0.0..TAU);
+        let z = random::uniform_range(-1.0..1.0);
         let r = (1.0 - z * z).sqrt();
         Vec3 {
             x: r * phi.cos(),
@@ -126,16 +123,6 @@ impl Vec3 {
     }
 }
 
-impl Default for Vec3 {
-    fn default() -> Self {
-        Self {
-            x: Default::default(),
-            y: Default::default(),
-            z: Default::default(),
-        }
-    }
-}
-
 impl IntoIterator for Vec3 {
     type Item = f64;
     type IntoIter = IntoIter<f64, 3>;